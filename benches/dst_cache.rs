@@ -0,0 +1,38 @@
+use std::hint::black_box;
+
+use chrono::{TimeDelta, TimeZone, Utc};
+use criterion::{criterion_group, criterion_main, Criterion};
+use timeapi::dst_cache::DstCache;
+use timeapi::timezone;
+
+fn zone_rules(c: &mut Criterion) {
+    // two weeks before the summer time ends, the Berlin rule stays valid
+    let time = Utc.with_ymd_and_hms(2026, 10, 11, 12, 0, 0).unwrap();
+    let end = time + TimeDelta::days(365);
+    let cache = DstCache::default();
+    let tz = chrono_tz::Europe::Berlin;
+
+    c.bench_function("zone offset fresh", |b| {
+        b.iter(|| timezone::zone_offset(black_box(&tz), black_box(time)))
+    });
+    c.bench_function("zone offset cached", |b| {
+        b.iter(|| {
+            cache
+                .rule(black_box(tz), black_box(time))
+                .zone_offset(&tz, time)
+        })
+    });
+    c.bench_function("transitions fresh", |b| {
+        b.iter(|| timezone::transitions(black_box(&tz), black_box(time), end))
+    });
+    c.bench_function("transitions cached", |b| {
+        b.iter(|| {
+            cache
+                .rule(black_box(tz), black_box(time))
+                .transitions(&tz, end)
+        })
+    });
+}
+
+criterion_group!(benches, zone_rules);
+criterion_main!(benches);
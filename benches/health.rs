@@ -0,0 +1,102 @@
+use std::{
+    fs,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::SystemTime,
+};
+
+use actix_web::{middleware::from_fn, rt::System, test, web, App};
+use chrono::Utc;
+use criterion::{criterion_group, criterion_main, Criterion};
+use timeapi::{app_context::AppContext, config::Config, middleware, routes};
+use tokio::sync::Semaphore;
+
+const MAX_CONCURRENT: usize = 8;
+
+/// Context serving a restored sync, `/now` reads it without any NTP request
+fn synced_context() -> Arc<AppContext> {
+    let path = std::env::temp_dir().join(format!("timeapi-bench-{}.json", std::process::id()));
+    let synced_at = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let cache = serde_json::json!({
+        "server": "bench",
+        "last_ntp": Utc::now().to_rfc3339(),
+        "last_updated": synced_at,
+    });
+    fs::write(&path, cache.to_string()).unwrap();
+
+    let app = AppContext::new(3600, "127.0.0.1:123".to_owned());
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    assert!(runtime.block_on(app.load_cache(&path)).unwrap());
+    fs::remove_file(path).unwrap();
+    Arc::new(app)
+}
+
+fn health(c: &mut Criterion) {
+    let system = System::new();
+    let config = Config::default();
+    let endpoints = config.endpoints.clone();
+    let context = synced_context();
+    let limit = Arc::new(Semaphore::new(MAX_CONCURRENT));
+
+    let service = system.block_on(test::init_service(
+        App::new()
+            .app_data(web::Data::from(context.clone()))
+            .app_data(web::Data::new(config))
+            .wrap(from_fn({
+                let limit = limit.clone();
+                move |req, next| middleware::concurrency_limit(req, next, Some(limit.clone()))
+            }))
+            .configure(|cfg| routes::configure(cfg, &endpoints)),
+    ));
+    let probe = || {
+        let req = test::TestRequest::get().uri("/health").to_request();
+        system.block_on(test::call_service(&service, req))
+    };
+
+    c.bench_function("health idle", |b| b.iter(probe));
+
+    // `/now` load: every permit held by in-flight requests while the spare
+    // cores keep reading the time cache, none are left to the probe otherwise
+    let spare = thread::available_parallelism().map_or(0, |cores| cores.get() - 1);
+    let stop = Arc::new(AtomicBool::new(false));
+    let load: Vec<_> = (0..spare.min(4))
+        .map(|_| {
+            let (context, stop) = (context.clone(), stop.clone());
+            thread::spawn(move || {
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .build()
+                    .unwrap();
+                while !stop.load(Ordering::Relaxed) {
+                    runtime.block_on(context.get_time());
+                }
+            })
+        })
+        .collect();
+    let held = system.block_on(limit.clone().acquire_many_owned(MAX_CONCURRENT as u32));
+    let req = test::TestRequest::get().uri("/now").to_request();
+    assert_eq!(
+        system.block_on(test::call_service(&service, req)).status(),
+        503
+    );
+
+    c.bench_function("health under now load", |b| {
+        b.iter(|| assert!(probe().status().is_success()))
+    });
+
+    drop(held);
+    stop.store(true, Ordering::Relaxed);
+    for thread in load {
+        thread.join().unwrap();
+    }
+}
+
+criterion_group!(benches, health);
+criterion_main!(benches);
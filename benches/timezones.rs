@@ -0,0 +1,33 @@
+use actix_web::{http::header, middleware::Compress, rt::System, test, web, App};
+use criterion::{criterion_group, criterion_main, Criterion};
+use timeapi::{config::Config, routes};
+
+fn timezones(c: &mut Criterion) {
+    let system = System::new();
+    let service = system.block_on(test::init_service(
+        App::new()
+            .app_data(web::Data::new(Config::default()))
+            .wrap(Compress::default())
+            .service(routes::timezones),
+    ));
+    // the whole list as one page is compressed by the middleware per request
+    let total = chrono_tz::TZ_VARIANTS.len();
+    let get = |uri: &str| {
+        let req = test::TestRequest::get()
+            .uri(uri)
+            .insert_header((header::ACCEPT_ENCODING, "gzip"))
+            .to_request();
+        let res = system.block_on(test::call_service(&service, req));
+        assert_eq!(res.headers().get(header::CONTENT_ENCODING).unwrap(), "gzip");
+        system.block_on(test::read_body(res))
+    };
+
+    c.bench_function("timezones gzip precompressed", |b| {
+        b.iter(|| get("/timezones"))
+    });
+    let page = format!("/timezones?limit={total}");
+    c.bench_function("timezones gzip per request", |b| b.iter(|| get(&page)));
+}
+
+criterion_group!(benches, timezones);
+criterion_main!(benches);
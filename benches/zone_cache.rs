@@ -0,0 +1,22 @@
+use std::hint::black_box;
+
+use chrono::Utc;
+use criterion::{criterion_group, criterion_main, Criterion};
+use timeapi::tz_database::TzDatabase;
+use timeapi::zone_cache::{self, ZoneCache};
+
+fn zone_conversion(c: &mut Criterion) {
+    let time = Utc::now();
+    let cache = ZoneCache::default();
+    let db = TzDatabase::default();
+
+    c.bench_function("zone conversion uncached", |b| {
+        b.iter(|| zone_cache::convert(&db, black_box(chrono_tz::Europe::Berlin), black_box(time)))
+    });
+    c.bench_function("zone conversion cached", |b| {
+        b.iter(|| cache.get(&db, black_box(chrono_tz::Europe::Berlin), black_box(time)))
+    });
+}
+
+criterion_group!(benches, zone_conversion);
+criterion_main!(benches);
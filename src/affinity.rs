@@ -0,0 +1,68 @@
+use std::{io, str::FromStr};
+
+use serde_derive::Serialize;
+
+/// Highest core index `sched_setaffinity` accepts with a default `cpu_set_t`
+const MAX_CORES: usize = 1024;
+
+/// Comma separated `CPU_AFFINITY` core indices, i.e. `0,2,3`. Empty leaves the
+/// affinity to the OS
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct CpuSet(pub Vec<usize>);
+
+impl FromStr for CpuSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|core| !core.is_empty())
+            .map(|core| match core.parse::<usize>() {
+                Ok(index) if index < MAX_CORES => Ok(index),
+                _ => Err(format!(
+                    "Expected a core index below {MAX_CORES}, got {core}"
+                )),
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl CpuSet {
+    /// Pin the calling thread, and the threads it spawns from now on, to the
+    /// cores. Called from `main` before the workers start so they inherit it
+    #[cfg(target_os = "linux")]
+    pub fn apply(&self) -> io::Result<()> {
+        // SAFETY: cpu_set_t is plain data, zeroed is the empty set and
+        // CPU_SET only receives indices below CPU_SETSIZE
+        let result = unsafe {
+            let mut set: libc::cpu_set_t = std::mem::zeroed();
+            for core in &self.0 {
+                libc::CPU_SET(*core, &mut set);
+            }
+            libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set)
+        };
+        match result {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn apply(&self) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "CPU affinity is only supported on Linux",
+        ))
+    }
+}
+
+#[test]
+fn test_cpu_set_parse() {
+    assert_eq!("0, 2,3".parse(), Ok(CpuSet(vec![0, 2, 3])));
+    assert_eq!("".parse(), Ok(CpuSet::default()));
+    assert!("0,a".parse::<CpuSet>().is_err());
+    assert!("-1".parse::<CpuSet>().is_err());
+    assert!("1024".parse::<CpuSet>().is_err());
+}
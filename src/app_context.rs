@@ -1,129 +1,1072 @@
 use std::{
-    borrow::Cow,
+    collections::HashMap,
+    fs, io,
     ops::{Deref, DerefMut},
-    time::SystemTime,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex, RwLock as StdRwLock},
+    time::{Duration, Instant, SystemTime},
 };
 
-use chrono::{DateTime, Months, TimeDelta, TimeZone, Utc};
-use log::{error, info, trace};
-use tokio::sync::RwLock;
+#[cfg(test)]
+use chrono::TimeZone;
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use chrono_tz::Tz;
+use log::{error, info, trace, warn};
+use ntp::formats::timestamp::TimestampFormat;
+use serde_derive::{Deserialize, Serialize};
+use tokio::sync::{Mutex as AsyncMutex, Notify, RwLock};
+
+use crate::{
+    dst_cache::{DstCache, ZoneRule},
+    leap::{LeapIndicator, ScheduledLeap},
+    metrics::Metrics,
+    models::{NtpSample, NtpStatus, NtpTest, Rounding, Time},
+    ntp_error::NtpError,
+    peer::{self, PeerError},
+    retry_budget::RetryBudget,
+    sntp::{self, NtpVersion},
+    tz_database::TzDatabase,
+    zone_cache::ZoneCache,
+};
+
+/// Seconds between the NTP era 0 epoch (1900-01-01) and the Unix epoch
+pub const NTP_UNIX_OFFSET: i64 = 2_208_988_800;
+
+/// Default for how far (in days) an NTP time may be from the system clock
+/// before it is rejected as implausible
+pub const DEFAULT_SANITY_WINDOW_DAYS: i64 = 10 * 365;
+
+/// Upper bound for the live readiness probe, kept short so probes don't pile up
+const READY_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+/// Retry period of a request waiting for the first sync during `WARMUP_WAIT_MS`
+const WARMUP_RETRY: Duration = Duration::from_millis(250);
+
+/// How `/readyz` decides whether the service is ready
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReadyProbe {
+    /// Ready while the cache holds a non expired NTP sync
+    #[default]
+    Cache,
+    /// Ready when a fresh NTP exchange succeeds
+    Live,
+}
+
+impl FromStr for ReadyProbe {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "cache" => Ok(Self::Cache),
+            "live" => Ok(Self::Live),
+            _ => Err(format!("Unknown ready probe {s}")),
+        }
+    }
+}
+
+/// Where a served time came from
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeSource {
+    /// Interpolated from the last NTP sync
+    Ntp,
+    /// Fetched from the peer node after every NTP server failed
+    Peer,
+    /// Interpolated from a sync persisted by a previous run, degraded until
+    /// the first new sync
+    Restored,
+    /// Fallback to the local system clock
+    System,
+}
+
+/// How far the time can be trusted, from its source and freshness:
+/// - `high` an NTP sync of this run younger than `CACHE_TIMEOUT`
+/// - `medium` interpolated from an older sync (NTP unreachable or within
+///   `MIN_SYNC_INTERVAL`), a restored sync, a sync from a server over
+///   `MAX_STRATUM`, or the peer node
+/// - `low` the local system clock
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Confidence {
+    High,
+    Medium,
+    Low,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeSample {
+    pub time: DateTime<Utc>,
+    pub source: TimeSource,
+    /// Seconds since the NTP sync the time is interpolated from
+    pub cache_age: Option<u64>,
+    /// Leap indicator of that sync
+    pub leap: LeapIndicator,
+    /// Unix time the sync happened at, per the system clock
+    pub synced_at: Option<i64>,
+    pub confidence: Confidence,
+    /// Exact time since the sync, `cache_age` is whole seconds
+    pub age: Option<Duration>,
+    /// Stratum of the NTP server of the sync, `None` when restored
+    pub stratum: Option<u8>,
+}
+
+impl TimeSample {
+    fn system() -> Self {
+        Self {
+            time: Utc::now(),
+            source: TimeSource::System,
+            cache_age: None,
+            leap: LeapIndicator::None,
+            synced_at: None,
+            confidence: Confidence::Low,
+            age: None,
+            stratum: None,
+        }
+    }
+}
 
 struct TimeCache {
+    /// NTP server the sync was made with
+    server: String,
     last_ntp: DateTime<Utc>,
     last_updated: SystemTime,
+    leap_indicator: LeapIndicator,
+    /// Leap second announced by this sync or still being smeared from a previous one
+    leap: Option<ScheduledLeap>,
+    /// Loaded from `CACHE_PERSIST_PATH` instead of synced by this run
+    restored: bool,
+    /// Raw transmit timestamp of the sync, `None` when restored
+    transmit: Option<TimestampFormat>,
+    /// Monotonic clock at the sync, `None` when restored
+    synced: Option<Instant>,
+    /// Stratum of `server`, `None` when restored
+    stratum: Option<u8>,
+}
+
+/// On disk form of the cache, written on shutdown and read back on startup
+#[derive(Deserialize, Serialize)]
+struct PersistedCache {
+    server: String,
+    last_ntp: String,
+    /// Unix time of the sync
+    last_updated: u64,
+}
+
+impl TimeCache {
+    /// Whole seconds since the last sync, `None` when the system clock went backwards
+    fn age(&self) -> Option<u64> {
+        self.elapsed().map(|dur| dur.as_secs())
+    }
+
+    /// Exact time since the last sync, `None` when the system clock went backwards
+    fn elapsed(&self) -> Option<Duration> {
+        SystemTime::now().duration_since(self.last_updated).ok()
+    }
+
+    /// How much further the system clock moved than the monotonic clock since
+    /// the sync, i.e. a host suspend or a manual clock change
+    fn clock_jump(&self) -> Option<TimeDelta> {
+        let monotonic = TimeDelta::from_std(self.synced?.elapsed()).ok()?;
+        let wall = match SystemTime::now().duration_since(self.last_updated) {
+            Ok(elapsed) => TimeDelta::from_std(elapsed).ok()?,
+            Err(err) => -TimeDelta::from_std(err.duration()).ok()?,
+        };
+        Some(wall - monotonic)
+    }
+
+    /// Time this sync would serve at `now`, unrounded and without smearing
+    fn extrapolate(&self, now: SystemTime) -> Option<DateTime<Utc>> {
+        let elapsed = now.duration_since(self.last_updated).ok()?;
+        Some(self.last_ntp + TimeDelta::from_std(elapsed).ok()?)
+    }
+
+    /// Sample `elapsed` after the sync, only `medium` confidence from a server
+    /// over `max_stratum`
+    fn sample(
+        &self,
+        elapsed: Duration,
+        smear: Option<TimeDelta>,
+        max_stratum: Option<u8>,
+    ) -> Option<TimeSample> {
+        let mut time = self
+            .last_ntp
+            .checked_add_signed(TimeDelta::from_std(elapsed).ok()?)?;
+        if let (Some(leap), Some(window)) = (self.leap, smear) {
+            time = leap.smear(time, window, self.last_ntp < leap.at);
+        }
+        Some(TimeSample {
+            time,
+            source: if self.restored {
+                TimeSource::Restored
+            } else {
+                TimeSource::Ntp
+            },
+            cache_age: Some(elapsed.as_secs()),
+            leap: self.leap_indicator,
+            synced_at: self.synced_at(),
+            confidence: self.confidence(max_stratum),
+            age: Some(elapsed),
+            stratum: self.stratum,
+        })
+    }
+
+    fn confidence(&self, max_stratum: Option<u8>) -> Confidence {
+        let distant = self
+            .stratum
+            .zip(max_stratum)
+            .is_some_and(|(stratum, max)| stratum > max);
+        if self.restored || distant {
+            Confidence::Medium
+        } else {
+            Confidence::High
+        }
+    }
+
+    fn synced_at(&self) -> Option<i64> {
+        self.last_updated
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .ok()
+            .map(|dur| dur.as_secs() as i64)
+    }
+}
+
+/// Run a blocking NTP exchange off the runtime, a panic in the ntp crate on a
+/// malformed response becomes an error instead of taking the worker down
+async fn guarded_request<F, T>(request: F) -> Result<T, NtpError>
+where
+    F: FnOnce() -> ntp::errors::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    let task = tokio::task::spawn_blocking(move || panic::catch_unwind(AssertUnwindSafe(request)));
+    match task.await {
+        Ok(Ok(res)) => res.map_err(NtpError::from),
+        Ok(Err(payload)) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|msg| msg.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_owned());
+            error!("NTP request panicked : {message}");
+            Err(NtpError::Panic(message))
+        }
+        Err(err) => Err(NtpError::Connection(err.to_string())),
+    }
+}
+
+struct NtpReading {
+    server: String,
+    time: DateTime<Utc>,
+    leap: LeapIndicator,
+    transmit: TimestampFormat,
+    stratum: u8,
+}
+
+/// Servers of an `NTP_SERVER` list by decreasing weight, `host:port*weight`
+/// with a weight of 1 when omitted. Equal weights keep the list order
+pub fn weighted_servers(list: &str) -> Result<Vec<String>, String> {
+    let mut servers = list
+        .split(',')
+        .map(str::trim)
+        .filter(|server| !server.is_empty())
+        .map(|server| match server.rsplit_once('*') {
+            Some((address, weight)) => match weight.trim().parse::<u32>() {
+                Ok(weight) if weight > 0 => Ok((address.trim().to_owned(), weight)),
+                _ => Err(format!("invalid weight {weight:?} of NTP server {address}")),
+            },
+            None => Ok((server.to_owned(), 1)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    servers.sort_by_key(|(_, weight)| std::cmp::Reverse(*weight));
+    Ok(servers.into_iter().map(|(address, _)| address).collect())
 }
 
 pub struct AppContext {
-    ntp_server: String,
+    /// Tried in order until one answers, replaced by `NTP_DISCOVERY`
+    ntp_servers: StdRwLock<Arc<[String]>>,
+    /// `NTP_SERVER`, used again when discovery fails
+    static_servers: Arc<[String]>,
+    peer: Option<String>,
     cache_timeout: u64,
+    /// Oldest persisted cache `load_cache` adopts, in seconds
+    persist_max_age: u64,
+    ready_probe: ReadyProbe,
+    sanity_window_days: i64,
+    leap_smear: Option<TimeDelta>,
+    rounding: Rounding,
+    min_sync_interval: Duration,
+    ntp_version: NtpVersion,
+    retry_budget: Option<RetryBudget>,
+    fail_on_degraded: bool,
+    strict_rest: bool,
+    max_step: Option<TimeDelta>,
+    max_clock_jump: Option<TimeDelta>,
+    /// Highest stratum still served with `high` confidence
+    max_stratum: Option<u8>,
+    started: Instant,
+    warmup_wait: Duration,
+    /// Notified on every successful NTP sync
+    synced: Notify,
+    /// Guarded by `sync_lock`
+    last_sync_attempt: Mutex<Option<Instant>>,
+    /// Held for a whole sync so only one runs at a time, the cache is only
+    /// locked to read it and to store the result
+    sync_lock: AsyncMutex<()>,
+    min_poll_interval: Duration,
+    /// Last exchange of a sync with each server
+    last_polls: Mutex<HashMap<String, Instant>>,
+    /// Last exchange of `/ntp/sample` or a live probe with each server, kept
+    /// apart so they never defer a sync
+    last_diagnostic_polls: Mutex<HashMap<String, Instant>>,
+    /// Served by `ntp_sample` when no new exchange is allowed
+    last_ntp_sample: Mutex<Option<NtpSample>>,
+    /// Answered by a live probe when no new exchange is allowed
+    last_probe: Mutex<Option<bool>>,
+    /// Last peer answer, `None` when it failed, and the monotonic clock at
+    /// it. Held during a fetch so concurrent misses wait for its outcome
+    peer_cache: AsyncMutex<Option<(Instant, Option<TimeSample>)>>,
     time_cache: RwLock<Option<TimeCache>>,
+    zone_cache: ZoneCache,
+    dst_cache: DstCache,
+    tz_database: TzDatabase,
+    metrics: Arc<Metrics>,
 }
 
 impl AppContext {
+    /// `ntp_server` is a comma separated list of servers tried by decreasing
+    /// weight then in order, see `weighted_servers`. An invalid list has no
+    /// server, `Config` rejects it first
     pub fn new(time_out: u64, ntp_server: String) -> Self {
+        let static_servers: Arc<[String]> = weighted_servers(&ntp_server)
+            .inspect_err(|err| error!("Ignored NTP_SERVER : {err}"))
+            .unwrap_or_default()
+            .into();
         Self {
-            ntp_server,
+            ntp_servers: StdRwLock::new(static_servers.clone()),
+            static_servers,
+            peer: None,
             cache_timeout: time_out,
+            persist_max_age: time_out,
+            ready_probe: ReadyProbe::default(),
+            sanity_window_days: DEFAULT_SANITY_WINDOW_DAYS,
+            leap_smear: None,
+            rounding: Rounding::default(),
+            min_sync_interval: Duration::ZERO,
+            ntp_version: NtpVersion::default(),
+            retry_budget: None,
+            fail_on_degraded: false,
+            strict_rest: false,
+            max_step: None,
+            max_clock_jump: None,
+            max_stratum: None,
+            started: Instant::now(),
+            warmup_wait: Duration::ZERO,
+            synced: Notify::new(),
+            last_sync_attempt: Mutex::new(None),
+            sync_lock: AsyncMutex::const_new(()),
+            min_poll_interval: Duration::ZERO,
+            last_polls: Mutex::default(),
+            last_diagnostic_polls: Mutex::default(),
+            last_ntp_sample: Mutex::default(),
+            last_probe: Mutex::default(),
+            peer_cache: AsyncMutex::const_new(None),
             time_cache: RwLock::new(None),
+            zone_cache: ZoneCache::default(),
+            dst_cache: DstCache::default(),
+            tz_database: TzDatabase::default(),
+            metrics: Arc::default(),
         }
     }
 
-    pub async fn get_time(&self) -> DateTime<Utc> {
+    pub fn with_ready_probe(mut self, ready_probe: ReadyProbe) -> Self {
+        self.ready_probe = ready_probe;
+        self
+    }
+
+    /// Ask the `/now` of a sibling node at `url` when every NTP server failed
+    pub fn with_peer(mut self, url: Option<String>) -> Self {
+        self.peer = url;
+        self
+    }
+
+    /// Reject NTP times further than `days` from the system clock
+    pub fn with_sanity_window(mut self, days: i64) -> Self {
+        self.sanity_window_days = days;
+        self
+    }
+
+    /// Smear announced leap seconds over `window` seconds instead of stepping
+    pub fn with_leap_smear(mut self, window: Option<u64>) -> Self {
+        self.leap_smear = window.map(|secs| TimeDelta::seconds(secs as i64));
+        self
+    }
+
+    /// Never query NTP more often than `interval`, even when the cache expired
+    pub fn with_min_sync_interval(mut self, interval: Duration) -> Self {
+        self.min_sync_interval = interval;
+        self
+    }
+
+    /// Never poll the same server more often than `interval`, RFC 4330 asks
+    /// for at least 15 seconds. A deferred server is skipped in the failover
+    pub fn with_min_poll_interval(mut self, interval: Duration) -> Self {
+        self.min_poll_interval = interval;
+        self
+    }
+
+    pub fn with_ntp_version(mut self, version: NtpVersion) -> Self {
+        self.ntp_version = version;
+        self
+    }
+
+    /// Allow at most `per_minute` NTP attempts across all requests, 0 is
+    /// unlimited. Once spent requests fall back without asking NTP
+    pub fn with_retry_budget(mut self, per_minute: u32) -> Self {
+        self.retry_budget = (per_minute > 0).then(|| RetryBudget::new(per_minute));
+        self
+    }
+
+    /// Refuse to serve the system clock fallback instead of an unverified time
+    pub fn with_fail_on_degraded(mut self, fail_on_degraded: bool) -> Self {
+        self.fail_on_degraded = fail_on_degraded;
+        self
+    }
+
+    /// Answer a well formed but unknown zone with `404` rather than `400`
+    pub fn with_strict_rest(mut self, strict_rest: bool) -> Self {
+        self.strict_rest = strict_rest;
+        self
+    }
+
+    pub fn strict_rest(&self) -> bool {
+        self.strict_rest
+    }
+
+    /// Warn and count a sync moving the served time by more than `millis`,
+    /// 0 never does. The new time is served either way
+    pub fn with_max_step(mut self, millis: u64) -> Self {
+        self.max_step = (millis > 0).then(|| TimeDelta::milliseconds(millis as i64));
+        self
+    }
+
+    /// Resync instead of interpolating once the system clock drifted more
+    /// than `millis` from the monotonic clock since the sync, 0 never does
+    pub fn with_clock_jump(mut self, millis: u64) -> Self {
+        self.max_clock_jump = (millis > 0).then(|| TimeDelta::milliseconds(millis as i64));
+        self
+    }
+
+    /// Adopt a persisted cache up to `secs` old on startup instead of the
+    /// cache timeout. An older one than the timeout is only served while NTP
+    /// can't be reached
+    pub fn with_persist_max_age(mut self, secs: u64) -> Self {
+        self.persist_max_age = secs;
+        self
+    }
+
+    /// Serve syncs from a server over `stratum` with `medium` confidence, 0
+    /// never does
+    pub fn with_max_stratum(mut self, stratum: u8) -> Self {
+        self.max_stratum = (stratum > 0).then_some(stratum);
+        self
+    }
+
+    /// For `wait` after startup a request that would fall back to the system
+    /// clock waits for the first NTP sync instead
+    pub fn with_warmup_wait(mut self, wait: Duration) -> Self {
+        self.warmup_wait = wait;
+        self
+    }
+
+    /// Cache per zone the rule in effect and its upcoming transitions
+    pub fn with_dst_cache(mut self, enabled: bool) -> Self {
+        self.dst_cache = DstCache::new(enabled);
+        self
+    }
+
+    pub fn zone_rule(&self, tz: Tz, at: DateTime<Utc>) -> ZoneRule {
+        self.dst_cache.rule(tz, at)
+    }
+
+    pub fn fail_on_degraded(&self) -> bool {
+        self.fail_on_degraded
+    }
+
+    pub fn with_rounding(mut self, rounding: Rounding) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    /// The sample time rounded to the second for display
+    pub fn display_time(&self, sample: &TimeSample) -> DateTime<Utc> {
+        self.rounding.apply(sample.time)
+    }
+
+    pub fn with_tz_database(mut self, tz_database: TzDatabase) -> Self {
+        self.tz_database = tz_database;
+        self
+    }
+
+    pub fn metrics(&self) -> &Arc<Metrics> {
+        &self.metrics
+    }
+
+    pub fn tz_database(&self) -> &TzDatabase {
+        &self.tz_database
+    }
+
+    /// Time of the instant in the zone, memoized per second
+    pub fn localize(&self, tz: Tz, time: DateTime<Utc>) -> Time {
+        self.zone_cache
+            .get(&self.tz_database, tz, time)
+            .with_subsec_nanos(time.timestamp_subsec_nanos())
+    }
+
+    /// Servers in the order they are tried
+    pub fn ntp_servers(&self) -> Arc<[String]> {
+        self.ntp_servers.read().unwrap().clone()
+    }
+
+    pub fn set_ntp_servers(&self, servers: Vec<String>) {
+        *self.ntp_servers.write().unwrap() = servers.into();
+    }
+
+    /// Back to the `NTP_SERVER` list
+    pub fn reset_ntp_servers(&self) {
+        *self.ntp_servers.write().unwrap() = self.static_servers.clone();
+    }
+
+    pub async fn is_ready(&self) -> bool {
+        match self.ready_probe {
+            ReadyProbe::Cache => self.is_synced().await,
+            ReadyProbe::Live => self.probe_ntp().await,
+        }
+    }
+
+    /// Whether the cache holds a non expired NTP sync made by this run
+    pub async fn is_synced(&self) -> bool {
         self.fast_get_time_from_cache()
             .await
-            .unwrap_or_else(|| async { self.update_and_return_new_time().await });
+            .is_some_and(|sample| sample.source == TimeSource::Ntp)
     }
 
-    async fn fast_get_time_from_cache(&self) -> Option<DateTime<Utc>> {
+    /// Ready when any of the servers answers. The exchanges go through
+    /// `start_diagnostic_poll`, when none is allowed the outcome of the last
+    /// sync or probe stands
+    async fn probe_ntp(&self) -> bool {
+        let probe = async {
+            let mut ret = Err(NtpError::Connection("no ntp server".to_owned()));
+            let version = self.ntp_version;
+            for address in self.ntp_servers().iter().cloned() {
+                if let Err(err) = self.start_diagnostic_poll(&address) {
+                    ret = Err(err);
+                    continue;
+                }
+                ret = guarded_request(move || sntp::request(address, version))
+                    .await
+                    .map(|_| ());
+                if ret.is_ok() {
+                    break;
+                }
+            }
+            ret
+        };
+
+        let ret = match tokio::time::timeout(READY_PROBE_TIMEOUT, probe).await {
+            Ok(res) => res,
+            Err(_) => Err(NtpError::Timeout),
+        };
+
+        if let Err(err @ NtpError::PollDeferred(_)) = &ret {
+            trace!("Ready probe not sent : {err}");
+            return self.is_synced().await || self.last_probe.lock().unwrap().unwrap_or(false);
+        }
+        let mut last_probe = self.last_probe.lock().unwrap();
+        let ready = ret
+            .inspect_err(|err| error!("Ready probe failed : {err}"))
+            .is_ok();
+        *last_probe = Some(ready);
+        ready
+    }
+
+    pub async fn get_time(&self) -> DateTime<Utc> {
+        self.get_sample().await.time
+    }
+
+    pub async fn get_sample(&self) -> TimeSample {
+        let mut sample = self.current_sample().await;
+        let deadline = self.started + self.warmup_wait;
+        while sample.source == TimeSource::System {
+            let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+                break;
+            };
+            trace!("Warming up, wait for the first sync");
+            // woken by a sync of another request, or retry ourselves
+            let _ = tokio::time::timeout(remaining.min(WARMUP_RETRY), self.synced.notified()).await;
+            sample = self.current_sample().await;
+        }
+        sample
+    }
+
+    async fn current_sample(&self) -> TimeSample {
+        match self.fast_get_time_from_cache().await {
+            Some(sample) => sample,
+            None => self.update_and_return_new_time().await,
+        }
+    }
+
+    /// State of the last NTP sync, without triggering one
+    pub async fn ntp_status(&self) -> NtpStatus {
+        let lock = self.time_cache.read().await;
+        let cache = lock.deref().as_ref();
+        let age = cache.and_then(TimeCache::age);
+        NtpStatus {
+            server: cache
+                .map(|cache| cache.server.clone())
+                .or_else(|| self.ntp_servers().first().cloned())
+                .unwrap_or_default(),
+            synced: age.is_some_and(|age| age < self.cache_timeout)
+                && cache.is_some_and(|cache| !cache.restored),
+            last_sync: cache.map(|cache| cache.last_ntp.to_rfc3339()),
+            cache_age_s: age,
+            leap: cache.map(|cache| cache.leap_indicator).unwrap_or_default(),
+            leap_at: cache
+                .and_then(|cache| cache.leap)
+                .map(|leap| leap.at.to_rfc3339()),
+            retry_budget: self.retry_budget.as_ref().map(RetryBudget::status),
+            transmit_timestamp: cache
+                .and_then(|cache| cache.transmit)
+                .map(|transmit| sntp::raw_timestamp(&transmit)),
+            transmit_time: cache
+                .and_then(|cache| cache.transmit.as_ref().and_then(sntp::unix_time))
+                .map(|time| time.to_rfc3339_opts(SecondsFormat::Nanos, true)),
+        }
+    }
+
+    /// Offset and delay estimate of the first server that answers. The
+    /// exchanges go through `start_diagnostic_poll`, when none is allowed the
+    /// last sample is served again as `cached`
+    pub async fn ntp_sample(&self) -> Result<NtpSample, NtpError> {
+        let mut ret = Err(NtpError::Connection("no ntp server".to_owned()));
+        for server in self.ntp_servers().iter() {
+            if let Err(err) = self.start_diagnostic_poll(server) {
+                info!("NTP sample from {server} not sent : {err}");
+                ret = Err(err);
+                continue;
+            }
+            let (address, version) = (server.clone(), self.ntp_version);
+            match guarded_request(move || sntp::estimate(address, version)).await {
+                Ok((_, estimate)) => {
+                    let sample = NtpSample {
+                        offset_ms: estimate.offset * 1000.0,
+                        delay_ms: estimate.delay * 1000.0,
+                        server: server.clone(),
+                        cached: false,
+                    };
+                    *self.last_ntp_sample.lock().unwrap() = Some(sample.clone());
+                    return Ok(sample);
+                }
+                Err(err) => {
+                    error!("NTP server {server} failed : {err}");
+                    ret = Err(err);
+                }
+            }
+        }
+
+        if let Err(NtpError::PollDeferred(_)) = ret {
+            if let Some(last) = self.last_ntp_sample.lock().unwrap().clone() {
+                return Ok(NtpSample {
+                    cached: true,
+                    ..last
+                });
+            }
+        }
+        ret
+    }
+
+    /// One-off exchange with `server` for diagnostics, it is neither cached
+    /// nor added to the configured servers
+    pub async fn test_ntp_server(&self, server: String) -> NtpTest {
+        let (address, version) = (server.clone(), self.ntp_version);
+        match guarded_request(move || sntp::estimate(address, version)).await {
+            Ok((packet, estimate)) => NtpTest {
+                server,
+                time: sntp::unix_time(&packet.transmit_time).map(|time| time.to_rfc3339()),
+                offset_ms: Some(estimate.offset * 1000.0),
+                delay_ms: Some(estimate.delay * 1000.0),
+                error: None,
+            },
+            Err(err) => NtpTest {
+                server,
+                time: None,
+                offset_ms: None,
+                delay_ms: None,
+                error: Some(err.to_string()),
+            },
+        }
+    }
+
+    /// Drop the cache so the next request syncs from NTP again, returns the
+    /// age of the flushed entry
+    pub async fn flush_cache(&self) -> Option<u64> {
+        let mut lock = self.time_cache.write().await;
+        let age = lock.deref().as_ref().and_then(TimeCache::age);
+        *lock.deref_mut() = None;
+        info!("Cache flushed, previous age {age:?}");
+        age
+    }
+
+    /// Write the cache to `path` so the next run can start from it, returns
+    /// whether there was anything to write
+    pub async fn save_cache(&self, path: &Path) -> io::Result<bool> {
+        let lock = self.time_cache.read().await;
+        let Some(cache) = lock.deref() else {
+            return Ok(false);
+        };
+        let persisted = PersistedCache {
+            server: cache.server.clone(),
+            last_ntp: cache.last_ntp.to_rfc3339(),
+            last_updated: cache.synced_at().unwrap_or_default() as u64,
+        };
+        let content = serde_json::to_vec(&persisted).map_err(io::Error::other)?;
+        fs::write(path, content)?;
+        info!("Cache saved to {}", path.display());
+        Ok(true)
+    }
+
+    /// Start from the cache saved at `path` when it is younger than
+    /// `persist_max_age`, it is served as `restored` until the first new sync
+    pub async fn load_cache(&self, path: &Path) -> io::Result<bool> {
+        let content = match fs::read(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(err) => return Err(err),
+        };
+        let persisted: PersistedCache = serde_json::from_slice(&content)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let last_ntp = DateTime::parse_from_rfc3339(&persisted.last_ntp)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?
+            .with_timezone(&Utc);
+
+        let cache = TimeCache {
+            server: persisted.server,
+            last_ntp,
+            last_updated: SystemTime::UNIX_EPOCH + Duration::from_secs(persisted.last_updated),
+            leap_indicator: LeapIndicator::None,
+            leap: None,
+            restored: true,
+            transmit: None,
+            synced: None,
+            stratum: None,
+        };
+        match cache.age() {
+            Some(age) if age < self.persist_max_age => {
+                info!(
+                    "Adopted the persisted cache from {}, {age}s old",
+                    path.display()
+                );
+                *self.time_cache.write().await = Some(cache);
+                Ok(true)
+            }
+            age => {
+                info!(
+                    "Rejected the persisted cache from {}, {age:?}s old is over {}s, sync fresh",
+                    path.display(),
+                    self.persist_max_age
+                );
+                Ok(false)
+            }
+        }
+    }
+
+    async fn fast_get_time_from_cache(&self) -> Option<TimeSample> {
         trace!("Read time from cache");
 
         let lock = self.time_cache.read().await;
+        let ret = self.fresh_sample(lock.deref().as_ref()?);
+        trace!("cache is not expired #2 {ret:?}");
+        ret
+    }
 
-        if let Some(time) = lock.deref() {
-            let duration: Result<i128, _> = SystemTime::now()
-                .duration_since(time.last_updated)
-                .map(|x| x.as_secs().into());
-            if duration
-                .as_ref()
-                .is_ok_and(|dur| (..self.cache_timeout.into()).contains(dur))
-            {
-                trace!("cache is not expired {duration:?}");
+    /// Sample of a cache that didn't expire, and whose interpolation the
+    /// system clock didn't jump across when `CLOCK_JUMP_MS` is set
+    fn fresh_sample(&self, time: &TimeCache) -> Option<TimeSample> {
+        let elapsed = time
+            .elapsed()
+            .filter(|elapsed| elapsed.as_secs() < self.cache_timeout)?;
+        trace!("cache is not expired {elapsed:?}");
 
-                let ret = time
-                    .last_ntp
-                    .checked_add_signed(TimeDelta::seconds(duration.unwrap() as i64));
+        let jump = self
+            .max_clock_jump
+            .zip(time.clock_jump())
+            .filter(|(max, jump)| jump.abs() > *max);
+        if let Some((_, jump)) = jump {
+            warn!(
+                "System clock jumped by {} ms since the last sync, resync",
+                jump.num_milliseconds()
+            );
+            return None;
+        }
+        time.sample(elapsed, self.leap_smear, self.max_stratum)
+    }
 
-                trace!("cache is not expired #2 {ret:?}");
-                return ret;
+    /// First good reading of the servers in order, the error of the last one otherwise
+    async fn get_time_from_ntp(&self) -> Result<NtpReading, NtpError> {
+        let mut ret = Err(NtpError::Connection("no ntp server".to_owned()));
+        for server in self.ntp_servers().iter() {
+            match self.start_poll(server) {
+                Ok(()) => {}
+                Err(err @ NtpError::PollDeferred(_)) => {
+                    info!("Poll of NTP server {server} deferred : {err}");
+                    ret = Err(err);
+                    continue;
+                }
+                Err(err) => return Err(err),
+            }
+            ret = self.read_ntp_server(server).await;
+            match &ret {
+                Ok(_) => break,
+                Err(err) => error!("NTP server {server} failed : {err}"),
             }
         }
+        ret
+    }
 
-        None
+    /// Clear an exchange with `server` unless it was polled under
+    /// `min_poll_interval` ago or the retry budget is exhausted, the poll is
+    /// only recorded once both allow it
+    fn start_poll(&self, server: &str) -> Result<(), NtpError> {
+        let mut polls = self.last_polls.lock().unwrap();
+        let now = Instant::now();
+        if let Some(elapsed) = polls.get(server).map(|last| now.duration_since(*last)) {
+            if elapsed < self.min_poll_interval {
+                return Err(NtpError::PollDeferred(elapsed));
+            }
+        }
+        if self
+            .retry_budget
+            .as_ref()
+            .is_some_and(|budget| !budget.try_acquire())
+        {
+            return Err(NtpError::BudgetExhausted);
+        }
+        if !self.min_poll_interval.is_zero() {
+            polls.insert(server.to_owned(), now);
+        }
+        Ok(())
+    }
+
+    /// Clear a diagnostic exchange with `server` unless a sync or another
+    /// diagnostic polled it under `min_poll_interval` ago. Only the diagnostic
+    /// poll is recorded and the retry budget is left to the syncs, so probes
+    /// and samples never hold a sync back
+    fn start_diagnostic_poll(&self, server: &str) -> Result<(), NtpError> {
+        let now = Instant::now();
+        let since = |polls: &HashMap<String, Instant>| {
+            polls.get(server).map(|last| now.duration_since(*last))
+        };
+        let mut diagnostics = self.last_diagnostic_polls.lock().unwrap();
+        let elapsed = since(&self.last_polls.lock().unwrap())
+            .into_iter()
+            .chain(since(&diagnostics))
+            .min();
+        if let Some(elapsed) = elapsed.filter(|elapsed| *elapsed < self.min_poll_interval) {
+            return Err(NtpError::PollDeferred(elapsed));
+        }
+        if !self.min_poll_interval.is_zero() {
+            diagnostics.insert(server.to_owned(), now);
+        }
+        Ok(())
     }
 
-    async fn get_time_from_ntp(&self) -> Result<DateTime<Utc>, Cow<'static, str>> {
-        let address = self.ntp_server.clone();
-        let response =
-            ntp::request(address).map_err(|err| format!("connection to ntp failed : {err}"))?;
+    async fn read_ntp_server(&self, server: &str) -> Result<NtpReading, NtpError> {
+        let address = server.to_owned();
+        let version = self.ntp_version;
+        let response = guarded_request(move || sntp::request(address, version)).await?;
 
-        let ntp_time = response.ref_time;
+        let ntp_time = response.transmit_time;
 
         info!("from ntp : {sec}", sec = ntp_time.sec);
 
-        let time = chrono::Utc
-            .timestamp_opt(ntp_time.sec as i64, 0)
-            .single()
-            .ok_or(format!("Error to get single time from {}", ntp_time.sec))?
-            .checked_sub_months(Months::new(70 * 12))
-            .ok_or(format!("Error to adjust time from {}", ntp_time.sec))?;
-        Ok(time)
+        let unix_time = i64::from(ntp_time.sec) - NTP_UNIX_OFFSET;
+        if unix_time < 0 {
+            return Err(NtpError::EpochOutOfRange(ntp_time.sec));
+        }
+
+        // keep the fraction of the transmit timestamp
+        let time = sntp::unix_time(&ntp_time).ok_or(NtpError::InvalidTime(unix_time))?;
+
+        let window = TimeDelta::days(self.sanity_window_days);
+        if (time - Utc::now()).abs() > window {
+            error!("Rejected implausible ntp time {time}");
+            return Err(NtpError::Implausible(time, self.sanity_window_days));
+        }
+        Ok(NtpReading {
+            server: server.to_owned(),
+            time,
+            leap: response.li.into(),
+            transmit: response.transmit_time,
+            stratum: response.stratum.get_value(),
+        })
     }
 
-    async fn update_and_return_new_time(&self) -> DateTime<Utc> {
-        info!("Update from ntp server");
+    /// Time of the peer node, its answer or failure is reused for
+    /// `cache_timeout` so an NTP outage doesn't ask it on every request
+    async fn get_time_from_peer(&self) -> Option<TimeSample> {
+        self.peer.as_ref()?;
+        let mut cache = self.peer_cache.lock().await;
+        if let Some((at, sample)) = *cache {
+            let elapsed = at.elapsed();
+            if elapsed.as_secs() < self.cache_timeout {
+                trace!("Reuse the peer answer of {elapsed:?} ago");
+                return sample.and_then(|sample| {
+                    Some(TimeSample {
+                        time: sample.time + TimeDelta::from_std(elapsed).ok()?,
+                        ..sample
+                    })
+                });
+            }
+        }
+        let sample = self.fetch_peer().await;
+        *cache = Some((Instant::now(), sample));
+        sample
+    }
 
-        let mut lock = self.time_cache.write().await;
+    async fn fetch_peer(&self) -> Option<TimeSample> {
+        let url = self.peer.clone()?;
+        let ret = match tokio::task::spawn_blocking(move || peer::fetch(&url)).await {
+            Ok(ret) => ret,
+            Err(err) => Err(PeerError::Request(err.to_string())),
+        };
+
+        let window = TimeDelta::days(self.sanity_window_days);
+        match ret {
+            Ok(time) if (time - Utc::now()).abs() <= window => Some(TimeSample {
+                time,
+                source: TimeSource::Peer,
+                cache_age: None,
+                leap: LeapIndicator::None,
+                synced_at: None,
+                confidence: Confidence::Medium,
+                age: None,
+                stratum: None,
+            }),
+            Ok(time) => {
+                error!("Rejected implausible peer time {time}");
+                None
+            }
+            Err(err) => {
+                error!("Error Get time from peer : {err}");
+                None
+            }
+        }
+    }
+
+    /// Interpolate from the cache whatever its age, or the system clock
+    fn stale_sample(&self, cache: Option<&TimeCache>) -> TimeSample {
+        cache
+            .and_then(|time| {
+                time.elapsed()
+                    .and_then(|elapsed| time.sample(elapsed, self.leap_smear, self.max_stratum))
+            })
+            .map(|sample| TimeSample {
+                confidence: Confidence::Medium,
+                ..sample
+            })
+            .unwrap_or_else(TimeSample::system)
+    }
 
-        if let Some(ref mut time) = lock.deref_mut() {
-            trace!("Use the cache");
-            let duration: Result<i128, _> = SystemTime::now()
-                .duration_since(time.last_updated)
-                .map(|x| x.as_secs().into());
+    async fn update_and_return_new_time(&self) -> TimeSample {
+        info!("Update from ntp server");
 
-            if duration
-                .as_ref()
-                .is_ok_and(|dur| (self.cache_timeout.into()..).contains(dur))
-            {
-                trace!("cache is time out");
-                if let Ok(val) = self.get_time_from_ntp().await {
-                    trace!("Update the cache");
-
-                    time.last_ntp = val;
-                    time.last_updated = SystemTime::now();
-                } else {
-                    trace!("Fallback to the value we have");
-                    return time
-                        .last_ntp
-                        .checked_add_signed(TimeDelta::seconds(duration.unwrap() as i64))
-                        .unwrap_or(chrono::Utc::now());
+        let _sync = self.sync_lock.lock().await;
+
+        {
+            let lock = self.time_cache.read().await;
+            if let Some(time) = lock.deref() {
+                trace!("Use the cache");
+                if let Some(sample) = self.fresh_sample(time) {
+                    trace!("cache was refreshed meanwhile");
+                    return sample;
                 }
+                trace!("cache is time out");
+            } else {
+                trace!("Instentiate the new cache");
             }
 
-            time.last_ntp
-        } else {
-            trace!("Instentiate the new cache");
-            let time = lock.deref_mut();
-
-            self.get_time_from_ntp()
-                .await
-                .inspect(|val| {
-                    *time = Some(TimeCache {
-                        last_ntp: *val,
-                        last_updated: SystemTime::now(),
+            let mut last_sync = self.last_sync_attempt.lock().unwrap();
+            if last_sync.is_some_and(|last| last.elapsed() < self.min_sync_interval) {
+                trace!("Synced less than MIN_SYNC_INTERVAL ago");
+                return self.stale_sample(lock.deref().as_ref());
+            }
+            *last_sync = Some(Instant::now());
+        }
+
+        // the exchanges run without the cache lock, readers keep being served
+        match self.get_time_from_ntp().await {
+            Ok(reading) => {
+                trace!("Update the cache");
+                let mut lock = self.time_cache.write().await;
+                let window = self.leap_smear.unwrap_or_default();
+                let leap = ScheduledLeap::announced(reading.leap, reading.time).or_else(|| {
+                    // keep smearing a leap the previous sync announced
+                    lock.deref()
+                        .as_ref()
+                        .and_then(|time| time.leap)
+                        .filter(|leap| reading.time < leap.at + window / 2)
+                });
+                if let Some(leap) = leap {
+                    info!("Leap second scheduled at {at}", at = leap.at);
+                }
+                let now = SystemTime::now();
+                let previous = lock.deref().as_ref().and_then(|time| time.extrapolate(now));
+                if let Some((max_step, previous)) = self.max_step.zip(previous) {
+                    let step = reading.time - previous;
+                    if step.abs() > max_step {
+                        warn!(
+                            "NTP sync from {server} stepped the time by {step} ms, over MAX_STEP_MS",
+                            server = reading.server,
+                            step = step.num_milliseconds()
+                        );
+                        self.metrics.record_clock_step();
+                    }
+                }
+                let cache = TimeCache {
+                    server: reading.server,
+                    last_ntp: reading.time,
+                    last_updated: now,
+                    leap_indicator: reading.leap,
+                    leap,
+                    restored: false,
+                    transmit: Some(reading.transmit),
+                    synced: Some(Instant::now()),
+                    stratum: Some(reading.stratum),
+                };
+                let sample = cache
+                    .sample(Duration::ZERO, self.leap_smear, self.max_stratum)
+                    .unwrap_or(TimeSample {
+                        time: reading.time,
+                        source: TimeSource::Ntp,
+                        cache_age: Some(0),
+                        leap: reading.leap,
+                        synced_at: cache.synced_at(),
+                        confidence: cache.confidence(self.max_stratum),
+                        age: Some(Duration::ZERO),
+                        stratum: cache.stratum,
                     });
-                })
-                .inspect_err(|_| error!("Error Get time from NTP"))
-                .unwrap_or(chrono::Utc::now())
+                *lock.deref_mut() = Some(cache);
+                self.synced.notify_waiters();
+                sample
+            }
+            Err(err) => {
+                error!("Error Get time from NTP : {err}");
+                if let Some(sample) = self.get_time_from_peer().await {
+                    return sample;
+                }
+                trace!("Fallback to the value we have");
+                self.stale_sample(self.time_cache.read().await.as_ref())
+            }
         }
     }
 }
@@ -143,15 +1086,647 @@ async fn test_fast_get_time_from_cache_fail_cache() {
 
 #[tokio::test]
 async fn test_fast_get_time_from_cache_success() {
-    let app = AppContext::new(5, "time.google.com:123".to_owned());
+    let ntp = crate::mock_ntp::MockNtpServer::start(Utc::now());
+    let app = AppContext::new(5, ntp.address());
     app.update_and_return_new_time().await;
     assert!(app.fast_get_time_from_cache().await.is_some());
 }
 
 #[tokio::test]
 async fn test_fast_get_time_from_cache_timeout() {
-    let app = AppContext::new(1, "time.google.com:123".to_owned());
+    let ntp = crate::mock_ntp::MockNtpServer::start(Utc::now());
+    let app = AppContext::new(1, ntp.address());
     app.update_and_return_new_time().await;
     let _ = tokio::time::sleep(std::time::Duration::from_secs(3)).await;
     assert!(app.fast_get_time_from_cache().await.is_none());
 }
+
+#[tokio::test]
+async fn test_get_time_from_mock_ntp() {
+    let injected = Utc.with_ymd_and_hms(2026, 10, 14, 8, 30, 0).unwrap();
+    let ntp = crate::mock_ntp::MockNtpServer::start(injected);
+    let app = AppContext::new(5, ntp.address());
+
+    assert_eq!(app.get_time().await, injected);
+    assert_eq!(ntp.requests(), 1);
+}
+
+#[tokio::test]
+async fn test_cached_sample_subsec() {
+    let injected =
+        Utc.with_ymd_and_hms(2026, 10, 14, 8, 30, 0).unwrap() + TimeDelta::nanoseconds(123_456_789);
+    let ntp = crate::mock_ntp::MockNtpServer::start(injected);
+    let app = AppContext::new(60, ntp.address());
+
+    // the fraction of the transmit timestamp survives, to the nanosecond
+    let synced = app.get_time().await;
+    assert!(
+        (synced - injected).abs() <= TimeDelta::nanoseconds(1),
+        "{synced}"
+    );
+
+    // cached samples add the exact elapsed time, not whole seconds
+    let cached = app.get_sample().await;
+    assert_eq!(ntp.requests(), 1);
+    assert!(cached.time >= synced && cached.time - synced < TimeDelta::seconds(1));
+    let lock = app.time_cache.read().await;
+    let later = lock
+        .as_ref()
+        .unwrap()
+        .sample(Duration::from_millis(1_250), None, None)
+        .unwrap();
+    assert_eq!(later.time - synced, TimeDelta::milliseconds(1_250));
+    assert_eq!(later.time.timestamp_subsec_millis(), 373);
+    assert_eq!(later.cache_age, Some(1));
+}
+
+#[tokio::test]
+async fn test_get_time_refreshes_from_mock_ntp() {
+    let first = Utc.with_ymd_and_hms(2026, 10, 14, 8, 30, 0).unwrap();
+    let second = Utc.with_ymd_and_hms(2026, 10, 14, 9, 0, 0).unwrap();
+    let ntp = crate::mock_ntp::MockNtpServer::start(first);
+    let app = AppContext::new(1, ntp.address());
+
+    assert_eq!(app.get_time().await, first);
+    ntp.set_time(second);
+    let _ = tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+    assert_eq!(app.get_time().await, second);
+    assert_eq!(ntp.requests(), 2);
+}
+
+#[tokio::test]
+async fn test_is_ready_cache_probe() {
+    let ntp = crate::mock_ntp::MockNtpServer::start(Utc::now());
+    let app = AppContext::new(5, ntp.address());
+    assert!(!app.is_ready().await);
+
+    app.get_time().await;
+    assert!(app.is_ready().await);
+}
+
+#[tokio::test]
+async fn test_is_ready_live_probe() {
+    let ntp = crate::mock_ntp::MockNtpServer::start(Utc::now());
+    let app = AppContext::new(5, ntp.address()).with_ready_probe(ReadyProbe::Live);
+    assert!(app.is_ready().await);
+    // the probe doesn't populate the cache
+    assert!(app.fast_get_time_from_cache().await.is_none());
+
+    // probes under MIN_POLL_INTERVAL repeat the last outcome and leave the
+    // poll slot and the retry budget to the sync
+    let app = AppContext::new(5, ntp.address())
+        .with_ready_probe(ReadyProbe::Live)
+        .with_min_poll_interval(Duration::from_secs(15))
+        .with_retry_budget(1);
+    let requests = ntp.requests();
+    for _ in 0..3 {
+        assert!(app.is_ready().await);
+    }
+    assert_eq!(ntp.requests(), requests + 1);
+    assert_eq!(app.get_sample().await.source, TimeSource::Ntp);
+    assert_eq!(ntp.requests(), requests + 2);
+    // and a probe right after the sync stands on it
+    app.last_probe.lock().unwrap().take();
+    assert!(app.is_ready().await);
+    assert_eq!(ntp.requests(), requests + 2);
+
+    let address = ntp.address();
+    drop(ntp);
+    let app = AppContext::new(5, address).with_ready_probe(ReadyProbe::Live);
+    assert!(!app.is_ready().await);
+}
+
+#[tokio::test]
+async fn test_get_time_from_ntp_connection_error() {
+    let app = AppContext::new(5, "aaa".to_owned());
+    assert!(matches!(
+        app.get_time_from_ntp().await,
+        Err(NtpError::Connection(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_get_time_from_ntp_timeout() {
+    let address = crate::mock_ntp::MockNtpServer::start(Utc::now()).address();
+    let app = AppContext::new(5, address);
+    assert!(matches!(
+        app.get_time_from_ntp().await,
+        Err(NtpError::Timeout)
+    ));
+}
+
+#[tokio::test]
+async fn test_get_time_from_ntp_epoch_out_of_range() {
+    let ntp =
+        crate::mock_ntp::MockNtpServer::start(Utc.with_ymd_and_hms(1960, 1, 1, 0, 0, 0).unwrap());
+    let app = AppContext::new(5, ntp.address());
+    assert!(matches!(
+        app.get_time_from_ntp().await,
+        Err(NtpError::EpochOutOfRange(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_get_time_from_ntp_implausible() {
+    use chrono::Timelike;
+
+    // era 0 NTP timestamps end in 2036, so the future case uses a short window
+    let next_year = (Utc::now() + TimeDelta::days(365))
+        .with_nanosecond(0)
+        .unwrap();
+    let ntp = crate::mock_ntp::MockNtpServer::start(next_year);
+    let app = AppContext::new(5, ntp.address()).with_sanity_window(30);
+    assert!(matches!(
+        app.get_time_from_ntp().await,
+        Err(NtpError::Implausible(time, 30)) if time == next_year
+    ));
+
+    // the unix epoch is as suspicious, and both fall back to the system clock
+    ntp.set_time(DateTime::UNIX_EPOCH);
+    let app = AppContext::new(5, ntp.address());
+    let sample = app.get_sample().await;
+    assert_eq!(sample.source, TimeSource::System);
+    assert!(app.fast_get_time_from_cache().await.is_none());
+
+    let app = AppContext::new(5, ntp.address()).with_sanity_window(365 * 100);
+    assert_eq!(app.get_time().await, DateTime::UNIX_EPOCH);
+}
+
+#[tokio::test]
+async fn test_leap_smear() {
+    let before_leap = Utc.with_ymd_and_hms(2026, 12, 31, 23, 59, 50).unwrap();
+    let ntp = crate::mock_ntp::MockNtpServer::start(before_leap);
+    ntp.set_leap(LeapIndicator::Insert);
+
+    // stepping mode serves the NTP time as is
+    let app = AppContext::new(5, ntp.address()).with_sanity_window(365 * 100);
+    assert_eq!(app.get_time().await, before_leap);
+
+    // 10 seconds before a centered day long window almost half the second is absorbed
+    let app = AppContext::new(1, ntp.address())
+        .with_sanity_window(365 * 100)
+        .with_leap_smear(Some(crate::leap::DEFAULT_SMEAR_WINDOW));
+    let behind = before_leap - app.get_time().await;
+    assert_eq!(
+        behind,
+        TimeDelta::nanoseconds(43_190 * 1_000_000_000 / 86_400)
+    );
+
+    // a resync after the leap without any announcement keeps smearing
+    let after_leap = Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap();
+    ntp.set_leap(LeapIndicator::None);
+    ntp.set_time(after_leap);
+    let _ = tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+    assert_eq!(
+        app.get_time().await,
+        after_leap + TimeDelta::milliseconds(500)
+    );
+}
+
+#[tokio::test]
+async fn test_ntp_status_leap_indicator() {
+    let ntp = crate::mock_ntp::MockNtpServer::start(Utc::now());
+    let app = AppContext::new(5, ntp.address());
+    let status = app.ntp_status().await;
+    assert!(!status.synced);
+    assert_eq!(status.leap, LeapIndicator::None);
+
+    for leap in [
+        LeapIndicator::Insert,
+        LeapIndicator::Delete,
+        LeapIndicator::Alarm,
+    ] {
+        ntp.set_leap(leap);
+        app.flush_cache().await;
+        assert_eq!(app.get_sample().await.leap, leap);
+
+        let status = app.ntp_status().await;
+        assert!(status.synced);
+        assert_eq!(status.leap, leap);
+        assert_eq!(status.leap_at.is_some(), leap != LeapIndicator::Alarm);
+    }
+}
+
+#[tokio::test]
+async fn test_guarded_request_panic() {
+    let ret = guarded_request(|| -> ntp::errors::Result<()> { panic!("malformed packet") }).await;
+    assert!(matches!(ret, Err(NtpError::Panic(msg)) if msg == "malformed packet"));
+
+    // the runtime survives and the next exchange goes through
+    let ntp = crate::mock_ntp::MockNtpServer::start(Utc::now());
+    let address = ntp.address();
+    assert!(guarded_request(move || ntp::request(address)).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_min_sync_interval() {
+    let ntp = crate::mock_ntp::MockNtpServer::start(Utc::now());
+    // a zero cache timeout makes every request want a sync
+    let app = AppContext::new(0, ntp.address()).with_min_sync_interval(Duration::from_secs(60));
+
+    for _ in 0..10 {
+        assert_eq!(app.get_sample().await.source, TimeSource::Ntp);
+    }
+    assert_eq!(ntp.requests(), 1);
+
+    let app = AppContext::new(0, ntp.address()).with_min_sync_interval(Duration::from_secs(1));
+    app.get_time().await;
+    app.get_time().await;
+    // once the interval passed NTP is asked again
+    if let Some(last) = app.last_sync_attempt.lock().unwrap().as_mut() {
+        *last -= Duration::from_secs(1);
+    }
+    app.get_time().await;
+    assert_eq!(ntp.requests(), 3);
+}
+
+#[tokio::test]
+async fn test_confidence() {
+    let ntp = crate::mock_ntp::MockNtpServer::start(Utc::now());
+    let app = AppContext::new(300, ntp.address());
+    assert_eq!(app.get_sample().await.confidence, Confidence::High);
+
+    // expired right away and not synced again within the interval
+    let app = AppContext::new(0, ntp.address()).with_min_sync_interval(Duration::from_secs(60));
+    assert_eq!(app.get_sample().await.confidence, Confidence::High);
+    let sample = app.get_sample().await;
+    assert_eq!(
+        (sample.source, sample.confidence),
+        (TimeSource::Ntp, Confidence::Medium)
+    );
+
+    let dead = crate::mock_ntp::MockNtpServer::start(Utc::now()).address();
+    let app = AppContext::new(300, dead);
+    let sample = app.get_sample().await;
+    assert_eq!(
+        (sample.source, sample.confidence),
+        (TimeSource::System, Confidence::Low)
+    );
+}
+
+#[tokio::test]
+async fn test_retry_budget() {
+    let ntp = crate::mock_ntp::MockNtpServer::start(Utc::now());
+    // a zero cache timeout makes every request want a sync
+    let app = AppContext::new(0, ntp.address()).with_retry_budget(2);
+
+    assert_eq!(app.get_sample().await.confidence, Confidence::High);
+    assert_eq!(app.get_sample().await.confidence, Confidence::High);
+    for _ in 0..5 {
+        let sample = app.get_sample().await;
+        assert_eq!(
+            (sample.source, sample.confidence),
+            (TimeSource::Ntp, Confidence::Medium)
+        );
+    }
+    assert_eq!(ntp.requests(), 2);
+    assert_eq!(
+        app.ntp_status().await.retry_budget,
+        Some(crate::retry_budget::RetryBudgetStatus {
+            per_minute: 2,
+            available: 0
+        })
+    );
+    assert_eq!(
+        AppContext::new(0, ntp.address())
+            .ntp_status()
+            .await
+            .retry_budget,
+        None
+    );
+}
+
+#[tokio::test]
+async fn test_ntp_server_failover() {
+    let dead = crate::mock_ntp::MockNtpServer::start(Utc::now()).address();
+    let injected = Utc.with_ymd_and_hms(2026, 10, 14, 8, 30, 0).unwrap();
+    let ntp = crate::mock_ntp::MockNtpServer::start(injected);
+    let app = AppContext::new(5, format!("{dead}, {}", ntp.address()));
+
+    let sample = app.get_sample().await;
+    assert_eq!((sample.time, sample.source), (injected, TimeSource::Ntp));
+    assert_eq!(app.ntp_status().await.server, ntp.address());
+}
+
+#[tokio::test]
+async fn test_peer_fallback() {
+    use chrono::Timelike;
+
+    // answers at once with a time out of the sanity window
+    let implausible =
+        crate::mock_ntp::MockNtpServer::start(Utc.with_ymd_and_hms(1990, 1, 1, 0, 0, 0).unwrap());
+    let dead = implausible.address();
+    let peer_time = Utc::now().with_nanosecond(0).unwrap();
+    let peer = crate::mock_peer::MockPeer::start(crate::mock_peer::now_body(peer_time, "ntp"));
+    let app = AppContext::new(5, dead.clone())
+        .with_sanity_window(30)
+        .with_peer(Some(peer.url()));
+
+    let sample = app.get_sample().await;
+    assert_eq!((sample.time, sample.source), (peer_time, TimeSource::Peer));
+    // peer times aren't an NTP sync, but the answer is reused until the cache
+    // timeout instead of asking the peer on every request
+    assert!(!app.is_synced().await);
+    let again = app.get_sample().await;
+    assert_eq!(again.source, TimeSource::Peer);
+    assert!(again.time >= peer_time && again.time - peer_time < TimeDelta::seconds(1));
+    assert_eq!(peer.requests(), 1);
+
+    // a peer on its own system clock is skipped for ours
+    let peer = crate::mock_peer::MockPeer::start(crate::mock_peer::now_body(peer_time, "system"));
+    let app = AppContext::new(5, dead)
+        .with_sanity_window(30)
+        .with_peer(Some(peer.url()));
+    assert_eq!(app.get_sample().await.source, TimeSource::System);
+}
+
+#[tokio::test]
+async fn test_flush_cache() {
+    let ntp = crate::mock_ntp::MockNtpServer::start(Utc::now());
+    let app = AppContext::new(5, ntp.address());
+    assert_eq!(app.flush_cache().await, None);
+
+    app.get_time().await;
+    assert!(app.fast_get_time_from_cache().await.is_some());
+    assert_eq!(app.flush_cache().await, Some(0));
+    assert!(app.fast_get_time_from_cache().await.is_none());
+}
+
+#[tokio::test]
+async fn test_cache_persistence() {
+    let path = std::env::temp_dir().join(format!("timeapi-cache-{}.json", std::process::id()));
+    let ntp = crate::mock_ntp::MockNtpServer::start(Utc::now());
+    let app = AppContext::new(300, ntp.address());
+    assert!(!app.save_cache(&path).await.unwrap());
+    let synced = app.get_sample().await;
+    assert!(app.save_cache(&path).await.unwrap());
+
+    // the restarted node doesn't ask NTP while the saved sync is fresh
+    let restarted = AppContext::new(300, ntp.address());
+    assert!(restarted.load_cache(&path).await.unwrap());
+    let sample = restarted.get_sample().await;
+    assert_eq!(ntp.requests(), 1);
+    assert_eq!(sample.source, TimeSource::Restored);
+    assert_eq!(sample.synced_at, synced.synced_at);
+    assert!((sample.time - synced.time).num_seconds().abs() <= 1);
+    assert!(!restarted.is_synced().await);
+    assert!(!restarted.ntp_status().await.synced);
+
+    let expired = AppContext::new(0, ntp.address());
+    assert!(!expired.load_cache(&path).await.unwrap());
+
+    fs::remove_file(&path).unwrap();
+    assert!(!restarted.load_cache(&path).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_cache_persist_max_age() {
+    let path = std::env::temp_dir().join(format!("timeapi-max-age-{}.json", std::process::id()));
+    let synced_at = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        - 600;
+    let cache = serde_json::json!({
+        "server": "previous:123",
+        "last_ntp": (Utc::now() - TimeDelta::seconds(600)).to_rfc3339(),
+        "last_updated": synced_at,
+    });
+    fs::write(&path, cache.to_string()).unwrap();
+
+    // 10 minutes old, past the default of the cache timeout
+    let ntp = crate::mock_ntp::MockNtpServer::start(Utc::now());
+    assert!(!AppContext::new(300, ntp.address())
+        .load_cache(&path)
+        .await
+        .unwrap());
+
+    let fresh_enough = AppContext::new(300, ntp.address()).with_persist_max_age(3600);
+    assert!(fresh_enough.load_cache(&path).await.unwrap());
+    assert_eq!(fresh_enough.ntp_status().await.server, "previous:123");
+
+    let too_old = AppContext::new(3600, ntp.address()).with_persist_max_age(60);
+    assert!(!too_old.load_cache(&path).await.unwrap());
+    assert_eq!(too_old.get_sample().await.source, TimeSource::Ntp);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn test_warmup_waits_for_sync() {
+    // rejected by the sanity window until the server is fixed
+    let ntp =
+        crate::mock_ntp::MockNtpServer::start(Utc.with_ymd_and_hms(1990, 1, 1, 0, 0, 0).unwrap());
+    let app = Arc::new(
+        AppContext::new(300, ntp.address())
+            .with_sanity_window(30)
+            .with_warmup_wait(Duration::from_secs(5)),
+    );
+
+    let start = Instant::now();
+    let request = tokio::spawn({
+        let app = app.clone();
+        async move { app.get_sample().await }
+    });
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    ntp.set_time(Utc::now());
+
+    let sample = request.await.unwrap();
+    assert_eq!(sample.source, TimeSource::Ntp);
+    assert!(start.elapsed() >= Duration::from_millis(400));
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    // without a window the fallback is immediate
+    let late =
+        crate::mock_ntp::MockNtpServer::start(Utc.with_ymd_and_hms(1990, 1, 1, 0, 0, 0).unwrap());
+    let app = AppContext::new(300, late.address()).with_sanity_window(30);
+    assert_eq!(app.get_sample().await.source, TimeSource::System);
+}
+
+#[tokio::test]
+async fn test_sync_without_cache_lock() {
+    // a server that never answers holds the sync for the whole socket timeout
+    let silent = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let app = Arc::new(AppContext::new(
+        300,
+        silent.local_addr().unwrap().to_string(),
+    ));
+
+    let sync = tokio::spawn({
+        let app = app.clone();
+        async move { app.get_sample().await }
+    });
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(!sync.is_finished());
+    tokio::time::timeout(Duration::from_millis(500), app.ntp_status())
+        .await
+        .expect("status served during the sync");
+    tokio::time::timeout(Duration::from_millis(500), app.fast_get_time_from_cache())
+        .await
+        .expect("cache read during the sync");
+    assert_eq!(sync.await.unwrap().source, TimeSource::System);
+}
+
+#[tokio::test]
+async fn test_ntp_status_transmit_timestamp() {
+    use chrono::Timelike;
+
+    let synced = Utc
+        .with_ymd_and_hms(2026, 10, 14, 8, 30, 15)
+        .unwrap()
+        .with_nanosecond(250_000_000)
+        .unwrap();
+    let ntp = crate::mock_ntp::MockNtpServer::start(synced);
+    let app = AppContext::new(300, ntp.address());
+    assert_eq!(app.ntp_status().await.transmit_timestamp, None);
+
+    app.get_sample().await;
+    let status = app.ntp_status().await;
+    let seconds = (synced.timestamp() + NTP_UNIX_OFFSET) as u64;
+    assert_eq!(
+        status.transmit_timestamp,
+        Some((seconds << 32) | 0x4000_0000)
+    );
+    assert_eq!(
+        status.transmit_time.as_deref(),
+        Some("2026-10-14T08:30:15.250000000Z")
+    );
+    // the served time still starts from the whole second
+    assert_eq!(app.get_sample().await.time.timestamp(), synced.timestamp());
+}
+
+#[tokio::test]
+async fn test_max_step() {
+    let first = Utc.with_ymd_and_hms(2026, 10, 14, 8, 30, 0).unwrap();
+    let ntp = crate::mock_ntp::MockNtpServer::start(first);
+    let app = AppContext::new(5, ntp.address()).with_max_step(2000);
+
+    async fn resync(app: &AppContext, elapsed: Duration) -> DateTime<Utc> {
+        if let Some(cache) = app.time_cache.write().await.as_mut() {
+            cache.last_updated -= elapsed;
+        }
+        app.update_and_return_new_time().await.time
+    }
+
+    assert_eq!(app.get_time().await, first);
+    assert_eq!(app.metrics().clock_steps(), 0);
+
+    // 10 seconds later the server jumped an hour ahead, and is still served
+    let jumped = first + TimeDelta::hours(1);
+    ntp.set_time(jumped);
+    assert_eq!(resync(&app, Duration::from_secs(10)).await, jumped);
+    assert_eq!(app.metrics().clock_steps(), 1);
+
+    // a regular sync keeps in step with the extrapolated cache
+    ntp.set_time(jumped + TimeDelta::seconds(10));
+    resync(&app, Duration::from_secs(10)).await;
+    assert_eq!(app.metrics().clock_steps(), 1);
+
+    // backward steps count too
+    ntp.set_time(first);
+    resync(&app, Duration::from_secs(10)).await;
+    assert_eq!(app.metrics().clock_steps(), 2);
+}
+
+#[test]
+fn test_weighted_servers() {
+    assert_eq!(
+        weighted_servers("a:123, b:123*3 ,c:123*3,d*2").unwrap(),
+        ["b:123", "c:123", "d", "a:123"]
+    );
+    assert_eq!(weighted_servers("a,b").unwrap(), ["a", "b"]);
+    for list in ["a*0", "a*-1", "a*", "a*two", "a,b*1.5"] {
+        assert!(weighted_servers(list).is_err(), "{list}");
+    }
+}
+
+#[tokio::test]
+async fn test_weighted_server_preferred() {
+    let listed = Utc.with_ymd_and_hms(2026, 10, 14, 8, 30, 0).unwrap();
+    let weighted = Utc.with_ymd_and_hms(2026, 10, 14, 9, 0, 0).unwrap();
+    let first = crate::mock_ntp::MockNtpServer::start(listed);
+    let second = crate::mock_ntp::MockNtpServer::start(weighted);
+    let servers = format!("{},{}*2", first.address(), second.address());
+
+    let app = AppContext::new(5, servers.clone()).with_sanity_window(30);
+    assert_eq!(app.get_time().await, weighted);
+    assert_eq!((first.requests(), second.requests()), (0, 1));
+
+    // the lighter server is still the fallback
+    second.set_time(Utc.with_ymd_and_hms(1990, 1, 1, 0, 0, 0).unwrap());
+    let app = AppContext::new(5, servers).with_sanity_window(30);
+    assert_eq!(app.get_time().await, listed);
+    assert_eq!((first.requests(), second.requests()), (1, 2));
+}
+
+#[tokio::test]
+async fn test_clock_jump_resyncs() {
+    let first = Utc.with_ymd_and_hms(2026, 10, 14, 8, 30, 0).unwrap();
+    let ntp = crate::mock_ntp::MockNtpServer::start(first);
+
+    // the system clock runs 30 seconds ahead of the monotonic one, as after a suspend
+    async fn jump(app: &AppContext) {
+        if let Some(cache) = app.time_cache.write().await.as_mut() {
+            cache.last_updated -= Duration::from_secs(30);
+        }
+    }
+
+    let app = AppContext::new(300, ntp.address());
+    assert_eq!(app.get_time().await, first);
+    jump(&app).await;
+    // interpolated 30 seconds and the fraction since the sync
+    assert_eq!((app.get_time().await - first).num_seconds(), 30);
+    assert_eq!(ntp.requests(), 1);
+
+    let app = AppContext::new(300, ntp.address()).with_clock_jump(1000);
+    assert_eq!(app.get_time().await, first);
+    let resynced = first + TimeDelta::minutes(5);
+    ntp.set_time(resynced);
+    jump(&app).await;
+    assert_eq!(app.get_time().await, resynced);
+    assert_eq!(ntp.requests(), 3);
+
+    // a restored cache has no monotonic reference and is interpolated as is
+    if let Some(cache) = app.time_cache.write().await.as_mut() {
+        cache.synced = None;
+    }
+    jump(&app).await;
+    assert_eq!((app.get_time().await - resynced).num_seconds(), 30);
+}
+
+#[tokio::test]
+async fn test_min_poll_interval() {
+    let first = Utc.with_ymd_and_hms(2026, 10, 14, 8, 30, 0).unwrap();
+    let ntp = crate::mock_ntp::MockNtpServer::start(first);
+    let other = crate::mock_ntp::MockNtpServer::start(first + TimeDelta::hours(1));
+    let servers = format!("{},{}", ntp.address(), other.address());
+    let app = AppContext::new(0, servers).with_min_poll_interval(Duration::from_secs(15));
+
+    // the cache always expired, each sync falls over to the next server not polled yet
+    assert_eq!(app.get_time().await, first);
+    assert_eq!(app.get_time().await, first + TimeDelta::hours(1));
+    app.get_time().await;
+    assert_eq!((ntp.requests(), other.requests()), (1, 1));
+    assert!(matches!(
+        app.get_time_from_ntp().await,
+        Err(NtpError::PollDeferred(elapsed)) if elapsed < Duration::from_secs(15)
+    ));
+
+    // once the interval passed the server is polled again
+    if let Some(last) = app.last_polls.lock().unwrap().get_mut(&ntp.address()) {
+        *last -= Duration::from_secs(15);
+    }
+    assert_eq!(app.get_time().await, first);
+    assert_eq!((ntp.requests(), other.requests()), (2, 1));
+
+    // an attempt the retry budget refuses sends nothing and defers nothing
+    let app = AppContext::new(0, ntp.address())
+        .with_min_poll_interval(Duration::from_secs(15))
+        .with_retry_budget(1);
+    assert!(app.start_poll("refused:123").is_ok());
+    assert!(matches!(
+        app.start_poll(&ntp.address()),
+        Err(NtpError::BudgetExhausted)
+    ));
+    assert!(!app.last_polls.lock().unwrap().contains_key(&ntp.address()));
+}
@@ -0,0 +1,94 @@
+//! Moon phase of an instant from the mean synodic month.
+//!
+//! The moon is assumed to move at its mean rate from a reference new moon, the
+//! eccentricity of its orbit and solar perturbations are ignored. The true
+//! phases are up to about 14 hours off the mean ones, so the phase name can be
+//! wrong near a boundary and the illumination by a few percent, which is fine
+//! for display but not for ephemerides.
+
+use std::f64::consts::TAU;
+
+use chrono::{DateTime, Utc};
+use serde_derive::Serialize;
+
+/// Mean length of a lunation in days
+const SYNODIC_MONTH: f64 = 29.530_588_853;
+/// Julian day of the new moon of 2000-01-06 18:14 UTC
+const REFERENCE_NEW_MOON: f64 = 2_451_550.1;
+const UNIX_EPOCH_JULIAN_DAY: f64 = 2_440_587.5;
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+/// One of the 8 phases, each centered on its fraction of the lunation
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MoonPhase {
+    New,
+    WaxingCrescent,
+    FirstQuarter,
+    WaxingGibbous,
+    Full,
+    WaningGibbous,
+    LastQuarter,
+    WaningCrescent,
+}
+
+const PHASES: [MoonPhase; 8] = [
+    MoonPhase::New,
+    MoonPhase::WaxingCrescent,
+    MoonPhase::FirstQuarter,
+    MoonPhase::WaxingGibbous,
+    MoonPhase::Full,
+    MoonPhase::WaningGibbous,
+    MoonPhase::LastQuarter,
+    MoonPhase::WaningCrescent,
+];
+
+/// `astro` object of `/now?astro=true`
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Astro {
+    pub moon_phase: MoonPhase,
+    /// Illuminated fraction of the disk, to 2 decimals
+    pub illumination: f64,
+}
+
+impl Astro {
+    pub fn at(at: DateTime<Utc>) -> Self {
+        let julian_day =
+            at.timestamp_millis() as f64 / 1000.0 / SECONDS_PER_DAY + UNIX_EPOCH_JULIAN_DAY;
+        // fraction of the lunation elapsed since the last mean new moon
+        let age = ((julian_day - REFERENCE_NEW_MOON) / SYNODIC_MONTH).rem_euclid(1.0);
+        let illumination = (1.0 - (TAU * age).cos()) / 2.0;
+
+        Self {
+            moon_phase: PHASES[(age * 8.0).round() as usize % PHASES.len()],
+            illumination: (illumination * 100.0).round() / 100.0,
+        }
+    }
+}
+
+#[test]
+fn test_moon_phase() {
+    use chrono::TimeZone;
+
+    let at = |y, m, d, h, min| Astro::at(Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap());
+
+    // the phases of January 2024
+    let new = at(2024, 1, 11, 11, 57);
+    assert_eq!(new.moon_phase, MoonPhase::New);
+    assert_eq!(new.illumination, 0.0);
+    assert_eq!(at(2024, 1, 18, 3, 53).moon_phase, MoonPhase::FirstQuarter);
+    let full = at(2024, 1, 25, 17, 54);
+    assert_eq!(full.moon_phase, MoonPhase::Full);
+    assert_eq!(full.illumination, 1.0);
+    assert_eq!(at(2024, 2, 2, 23, 18).moon_phase, MoonPhase::LastQuarter);
+
+    assert_eq!(
+        at(2024, 1, 22, 0, 0),
+        Astro {
+            moon_phase: MoonPhase::WaxingGibbous,
+            illumination: 0.83,
+        }
+    );
+    // the full moon of 1999-12-22, before the reference new moon
+    assert_eq!(at(1999, 12, 22, 17, 31).moon_phase, MoonPhase::Full);
+}
@@ -0,0 +1,631 @@
+use std::{env, fmt::Debug, fmt::Display, net::IpAddr, str::FromStr};
+
+use actix_web::http::StatusCode;
+use color_eyre::eyre::eyre;
+use log::LevelFilter;
+use serde_derive::Serialize;
+
+use crate::{
+    affinity::CpuSet,
+    app_context::{self, ReadyProbe, DEFAULT_SANITY_WINDOW_DAYS},
+    discovery::DiscoverySource,
+    leap::DEFAULT_SMEAR_WINDOW,
+    models::{FieldNames, Precision, Rounding},
+    routes::ROUTE_METHODS,
+    sntp::NtpVersion,
+    tls::TlsVersion,
+};
+
+const DEFAULT_IP_ADDRESS: &str = "127.0.0.1";
+const DEFAULT_NTP_SERVER: &str = "time.google.com:123";
+const DEFAULT_PORT: u16 = 3000;
+const DEFAULT_CACHE_DURATION: u64 = 5 * 60; // sec
+const DEFAULT_CORS_ORIGIN: &str = "127.0.0.1";
+const DEFAULT_COMPRESSION_MIN_BYTES: u64 = 1024;
+const DEFAULT_MAX_BODY_BYTES: usize = 64 * 1024;
+const DEFAULT_TIMEZONES_MAX_AGE: u32 = 86400; // seconds
+const DEFAULT_DISCOVERY_INTERVAL: u64 = 5 * 60; // sec
+/// RFC 4330 clients should not poll a server more often than every 15 seconds
+const DEFAULT_MIN_POLL_INTERVAL: u64 = 15; // sec
+
+/// Status `/health` answers with, some load balancers insist on 204
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct HealthStatus(u16);
+
+impl Default for HealthStatus {
+    fn default() -> Self {
+        Self(200)
+    }
+}
+
+impl FromStr for HealthStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<u16>() {
+            Ok(code @ 200..=299) => Ok(Self(code)),
+            _ => Err(format!("{s} is not a 2xx status code")),
+        }
+    }
+}
+
+impl HealthStatus {
+    pub fn status_code(self) -> StatusCode {
+        StatusCode::from_u16(self.0).unwrap_or(StatusCode::OK)
+    }
+}
+
+/// One listening socket, `host:port` optionally suffixed with `@admin` to
+/// restrict the `/admin` endpoints to it
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BindAddress {
+    pub address: String,
+    pub admin: bool,
+}
+
+/// Comma separated `BIND` list, empty to listen on `IP:PORT` only
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct BindList(pub Vec<BindAddress>);
+
+impl FromStr for BindList {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let list = s
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (address, admin) = match entry.strip_suffix("@admin") {
+                    Some(address) => (address, true),
+                    None => (entry, false),
+                };
+                match address.rsplit_once(':') {
+                    Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {
+                        Ok(BindAddress {
+                            address: address.to_owned(),
+                            admin,
+                        })
+                    }
+                    _ => Err(format!("Expected host:port, got {entry}")),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if list.is_empty() {
+            return Err("No address to bind".to_owned());
+        }
+        Ok(Self(list))
+    }
+}
+
+/// `addr/prefix` network, a bare address is a single host network
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        // an IPv4 client on a dual stack socket shows up as `::ffff:a.b.c.d`
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+            IpAddr::V4(_) => ip,
+        };
+        let (addr, ip, bits) = match (self.addr, ip) {
+            (IpAddr::V4(addr), IpAddr::V4(ip)) => {
+                (u32::from(addr).into(), u32::from(ip).into(), 32)
+            }
+            (IpAddr::V6(addr), IpAddr::V6(ip)) => (u128::from(addr), u128::from(ip), 128),
+            _ => return false,
+        };
+        let mask = u128::MAX
+            .checked_shl(u32::from(bits - self.prefix))
+            .unwrap_or(0);
+        addr & mask == ip & mask
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = s
+            .split_once('/')
+            .map_or((s, None), |(addr, prefix)| (addr, Some(prefix)));
+        let addr: IpAddr = addr
+            .parse()
+            .map_err(|_| format!("Invalid address in {s}"))?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse()
+                .ok()
+                .filter(|prefix| *prefix <= max)
+                .ok_or_else(|| format!("Invalid prefix length in {s}, expected 0 to {max}"))?,
+            None => max,
+        };
+        Ok(Self { addr, prefix })
+    }
+}
+
+impl Display for Cidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+impl serde::Serialize for Cidr {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Comma separated `ALLOW_IPS` / `DENY_IPS` networks
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct CidrList(pub Vec<Cidr>);
+
+impl FromStr for CidrList {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+/// Comma separated route patterns as listed in `ROUTE_METHODS`, i.e.
+/// `/now,/now/{continent}/{region},/health`
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct EndpointList(Vec<String>);
+
+impl FromStr for EndpointList {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                ROUTE_METHODS
+                    .iter()
+                    .any(|(pattern, _)| *pattern == entry)
+                    .then(|| entry.to_owned())
+                    .ok_or_else(|| format!("Unknown endpoint {entry}"))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+/// Comma separated `RATE_LIMITS` route patterns as listed in `ROUTE_METHODS`
+/// with their limit, i.e. `/now=100,/offsets=10`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RateLimitList(pub Vec<(String, u32)>);
+
+impl FromStr for RateLimitList {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (pattern, limit) = entry
+                    .split_once('=')
+                    .ok_or_else(|| format!("Invalid rate limit {entry}, expected pattern=limit"))?;
+                let pattern = pattern.trim();
+                if !ROUTE_METHODS.iter().any(|(known, _)| *known == pattern) {
+                    return Err(format!("Unknown endpoint {pattern}"));
+                }
+                let limit = limit
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid rate limit {entry}"))?;
+                Ok((pattern.to_owned(), limit))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl serde::Serialize for RateLimitList {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(pattern, limit)| (pattern, limit)))
+    }
+}
+
+/// Routes registered at startup, everything in `ENABLED_ENDPOINTS` (all by
+/// default) but `DISABLED_ENDPOINTS`. The `/debug/` routes also need
+/// `DEBUG_ENDPOINTS`
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Endpoints {
+    pub enabled: Option<EndpointList>,
+    pub disabled: EndpointList,
+    pub debug: bool,
+}
+
+impl Endpoints {
+    pub fn is_enabled(&self, pattern: &str) -> bool {
+        let listed = |list: &EndpointList| list.0.iter().any(|entry| entry == pattern);
+        (self.debug || !pattern.starts_with("/debug/"))
+            && self.enabled.as_ref().is_none_or(listed)
+            && !listed(&self.disabled)
+    }
+
+    /// Enabled patterns in `ROUTE_METHODS` order
+    pub fn active(&self) -> Vec<&'static str> {
+        ROUTE_METHODS
+            .iter()
+            .map(|(pattern, _)| *pattern)
+            .filter(|pattern| self.is_enabled(pattern))
+            .collect()
+    }
+}
+
+/// Effective runtime configuration, served as is by `/config` so anything
+/// sensitive must be `#[serde(skip)]`: secrets, URLs that may embed
+/// credentials, the IP access lists and host file paths
+#[derive(Clone, Debug, Serialize)]
+pub struct Config {
+    pub log_level: LevelFilter,
+    pub address: String,
+    pub port: u16,
+    pub bind: BindList,
+    pub port_retry: bool,
+    pub ntp_server: String,
+    pub ntp_version: NtpVersion,
+    #[serde(skip)]
+    pub peer_url: Option<String>,
+    #[serde(skip)]
+    pub ntp_discovery: Option<DiscoverySource>,
+    pub ntp_discovery_interval: u64,
+    pub cache_timeout: u64,
+    #[serde(skip)]
+    pub cache_persist_path: Option<String>,
+    pub cache_persist_max_age: u64,
+    pub min_sync_interval: u64,
+    pub min_poll_interval: u64,
+    pub max_step_ms: u64,
+    pub clock_jump_ms: u64,
+    pub max_stratum: u8,
+    pub ntp_retry_budget: u32,
+    pub fail_on_degraded: bool,
+    pub warmup_wait_ms: u64,
+    pub strict_rest: bool,
+    pub sanity_window_days: i64,
+    pub leap_smear: bool,
+    pub leap_smear_window: u64,
+    pub rounding: Rounding,
+    pub field_names: FieldNames,
+    pub default_precision: Precision,
+    pub cors_origin: String,
+    pub trust_proxy: bool,
+    pub slow_request_ms: u64,
+    pub allowed_hosts: Vec<String>,
+    #[serde(skip)]
+    pub allow_ips: CidrList,
+    #[serde(skip)]
+    pub deny_ips: CidrList,
+    pub compression_min_bytes: u64,
+    pub max_concurrent: usize,
+    pub rate_limit: u32,
+    pub rate_limits: RateLimitList,
+    pub max_body_bytes: usize,
+    pub timezones_max_age: u32,
+    pub ready_probe: ReadyProbe,
+    pub watchdog: bool,
+    pub self_check: bool,
+    pub health_status: HealthStatus,
+    #[serde(skip)]
+    pub tls_cert: Option<String>,
+    #[serde(skip)]
+    pub tls_key: Option<String>,
+    #[serde(skip)]
+    pub admin_token: Option<String>,
+    pub tls_min_version: TlsVersion,
+    #[serde(skip)]
+    pub tzdata_dir: Option<String>,
+    pub dst_cache: bool,
+    pub endpoints: Endpoints,
+    pub cpu_affinity: CpuSet,
+}
+
+impl Config {
+    pub fn from_env() -> color_eyre::Result<Self> {
+        Self::from_vars(|name| env::var(name).ok())
+    }
+
+    /// Configuration with the variables `var` returns instead of the process
+    /// environment
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> color_eyre::Result<Self> {
+        let ntp_server = parse_env(&var, "NTP_SERVER", DEFAULT_NTP_SERVER.to_owned());
+        app_context::weighted_servers(&ntp_server)
+            .map_err(|err| eyre!("Invalid NTP_SERVER value {ntp_server:?} : {err}"))?;
+        let cache_timeout = parse_env(&var, "CACHE_TIMEOUT", DEFAULT_CACHE_DURATION);
+        Ok(Self {
+            log_level: parse_env(&var, "LOG_LEVEL", LevelFilter::Info),
+            address: parse_env(&var, "IP", DEFAULT_IP_ADDRESS.to_owned()),
+            port: parse_env(&var, "PORT", DEFAULT_PORT),
+            bind: try_parse_env(&var, "BIND", BindList::default())?,
+            port_retry: parse_env(&var, "PORT_RETRY", false),
+            ntp_server,
+            ntp_version: try_parse_env(&var, "NTP_VERSION", NtpVersion::default())?,
+            peer_url: var("PEER_URL"),
+            ntp_discovery: var("NTP_DISCOVERY")
+                .map(|source| {
+                    source
+                        .parse()
+                        .map_err(|err| eyre!("Invalid NTP_DISCOVERY value {source:?} : {err}"))
+                })
+                .transpose()?,
+            ntp_discovery_interval: parse_env(
+                &var,
+                "NTP_DISCOVERY_INTERVAL",
+                DEFAULT_DISCOVERY_INTERVAL,
+            ),
+            cache_timeout,
+            cache_persist_path: var("CACHE_PERSIST_PATH"),
+            cache_persist_max_age: parse_env(&var, "CACHE_PERSIST_MAX_AGE", cache_timeout),
+            min_sync_interval: parse_env(&var, "MIN_SYNC_INTERVAL", 0),
+            min_poll_interval: parse_env(&var, "MIN_POLL_INTERVAL", DEFAULT_MIN_POLL_INTERVAL),
+            max_step_ms: parse_env(&var, "MAX_STEP_MS", 0),
+            clock_jump_ms: parse_env(&var, "CLOCK_JUMP_MS", 0),
+            max_stratum: parse_env(&var, "MAX_STRATUM", 0),
+            ntp_retry_budget: parse_env(&var, "NTP_RETRY_BUDGET", 0),
+            fail_on_degraded: parse_env(&var, "FAIL_ON_DEGRADED", false),
+            warmup_wait_ms: parse_env(&var, "WARMUP_WAIT_MS", 0),
+            strict_rest: parse_env(&var, "STRICT_REST", false),
+            sanity_window_days: parse_env(&var, "SANITY_WINDOW_DAYS", DEFAULT_SANITY_WINDOW_DAYS),
+            leap_smear: parse_env(&var, "LEAP_SMEAR", false),
+            leap_smear_window: parse_env(&var, "LEAP_SMEAR_WINDOW", DEFAULT_SMEAR_WINDOW),
+            rounding: parse_env(&var, "ROUNDING", Rounding::default()),
+            field_names: try_parse_env(&var, "FIELD_NAMES", FieldNames::default())?,
+            default_precision: parse_env(&var, "DEFAULT_PRECISION", Precision::default()),
+            cors_origin: parse_env(&var, "CORS_ORIGIN", DEFAULT_CORS_ORIGIN.to_owned()),
+            trust_proxy: parse_env(&var, "TRUST_PROXY", false),
+            slow_request_ms: parse_env(&var, "SLOW_REQUEST_MS", 0),
+            allowed_hosts: var("ALLOWED_HOSTS")
+                .map(|hosts| {
+                    hosts
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|host| !host.is_empty())
+                        .map(str::to_owned)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            allow_ips: try_parse_env(&var, "ALLOW_IPS", CidrList::default())?,
+            deny_ips: try_parse_env(&var, "DENY_IPS", CidrList::default())?,
+            compression_min_bytes: parse_env(
+                &var,
+                "COMPRESSION_MIN_BYTES",
+                DEFAULT_COMPRESSION_MIN_BYTES,
+            ),
+            max_concurrent: parse_env(&var, "MAX_CONCURRENT", 0),
+            rate_limit: try_parse_env(&var, "RATE_LIMIT", 0)?,
+            rate_limits: try_parse_env(&var, "RATE_LIMITS", RateLimitList::default())?,
+            max_body_bytes: parse_env(&var, "MAX_BODY_BYTES", DEFAULT_MAX_BODY_BYTES),
+            timezones_max_age: try_parse_env(&var, "TIMEZONES_MAX_AGE", DEFAULT_TIMEZONES_MAX_AGE)?,
+            ready_probe: parse_env(&var, "READY_PROBE", ReadyProbe::Cache),
+            watchdog: parse_env(&var, "WATCHDOG", false),
+            self_check: parse_env(&var, "SELF_CHECK", false),
+            health_status: try_parse_env(&var, "HEALTH_STATUS", HealthStatus::default())?,
+            tls_cert: var("TLS_CERT"),
+            tls_key: var("TLS_KEY"),
+            admin_token: var("ADMIN_TOKEN").filter(|token| !token.trim().is_empty()),
+            tls_min_version: try_parse_env(&var, "TLS_MIN_VERSION", TlsVersion::default())?,
+            tzdata_dir: var("TZDATA_DIR"),
+            dst_cache: parse_env(&var, "DST_CACHE", true),
+            endpoints: Endpoints {
+                enabled: var("ENABLED_ENDPOINTS")
+                    .map(|list| {
+                        list.parse().map_err(|err| {
+                            eyre!("Invalid ENABLED_ENDPOINTS value {list:?} : {err}")
+                        })
+                    })
+                    .transpose()?,
+                disabled: try_parse_env(&var, "DISABLED_ENDPOINTS", EndpointList::default())?,
+                debug: parse_env(&var, "DEBUG_ENDPOINTS", false),
+            },
+            cpu_affinity: try_parse_env(&var, "CPU_AFFINITY", CpuSet::default())?,
+        })
+    }
+
+    /// Sockets to listen on, `BIND` or else `IP:PORT`
+    pub fn listeners(&self) -> Vec<BindAddress> {
+        if self.bind.0.is_empty() {
+            vec![BindAddress {
+                address: format!("{}:{}", self.address, self.port),
+                admin: false,
+            }]
+        } else {
+            self.bind.0.clone()
+        }
+    }
+}
+
+/// Every setting at its default, whatever the process environment
+impl Default for Config {
+    fn default() -> Self {
+        Self::from_vars(|_| None).expect("valid default configuration")
+    }
+}
+
+#[inline]
+fn parse_env<T>(var: &impl Fn(&str) -> Option<String>, name: &str, default: T) -> T
+where
+    T: FromStr + Debug,
+{
+    var(name)
+        .and_then(|val| {
+            println!("Get the {name} value from env:{val:?}");
+            val.parse::<T>().ok()
+        })
+        .unwrap_or_else(|| {
+            println!("Get the default value fro {name} :{default:?}");
+            default
+        })
+}
+
+/// Like `parse_env` but an invalid value is a startup error instead of
+/// silently using the default, for settings where a typo would be unsafe
+fn try_parse_env<T>(
+    var: &impl Fn(&str) -> Option<String>,
+    name: &str,
+    default: T,
+) -> color_eyre::Result<T>
+where
+    T: FromStr + Debug,
+    T::Err: Display,
+{
+    match var(name) {
+        Some(val) => {
+            println!("Get the {name} value from env:{val:?}");
+            val.parse::<T>()
+                .map_err(|err| eyre!("Invalid {name} value {val:?} : {err}"))
+        }
+        None => {
+            println!("Get the default value fro {name} :{default:?}");
+            Ok(default)
+        }
+    }
+}
+
+#[test]
+fn test_health_status_parse() {
+    assert_eq!("204".parse(), Ok(HealthStatus(204)));
+    assert_eq!(HealthStatus::default().status_code(), StatusCode::OK);
+    assert!("404".parse::<HealthStatus>().is_err());
+    assert!("ok".parse::<HealthStatus>().is_err());
+}
+
+#[test]
+fn test_bind_list_parse() {
+    let list: BindList = "0.0.0.0:3000, 127.0.0.1:3001@admin,[::1]:3002"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        list.0,
+        vec![
+            BindAddress {
+                address: "0.0.0.0:3000".to_owned(),
+                admin: false
+            },
+            BindAddress {
+                address: "127.0.0.1:3001".to_owned(),
+                admin: true
+            },
+            BindAddress {
+                address: "[::1]:3002".to_owned(),
+                admin: false
+            },
+        ]
+    );
+
+    assert!("0.0.0.0".parse::<BindList>().is_err());
+    assert!("0.0.0.0:http".parse::<BindList>().is_err());
+    assert!(":3000".parse::<BindList>().is_err());
+    assert!(" , ".parse::<BindList>().is_err());
+}
+
+#[test]
+fn test_cidr_parse() {
+    let list: CidrList = "10.0.0.0/8, 192.168.1.7,fd00::/8".parse().unwrap();
+    assert_eq!(
+        list.0.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        ["10.0.0.0/8", "192.168.1.7/32", "fd00::/8"]
+    );
+    assert!(list.0[0].contains("10.200.3.4".parse().unwrap()));
+    assert!(!list.0[0].contains("11.0.0.1".parse().unwrap()));
+    assert!(list.0[0].contains("::ffff:10.1.2.3".parse().unwrap()));
+    assert!(list.0[1].contains("192.168.1.7".parse().unwrap()));
+    assert!(!list.0[1].contains("192.168.1.8".parse().unwrap()));
+    assert!(list.0[2].contains("fd12::1".parse().unwrap()));
+    assert!(!list.0[2].contains("10.0.0.1".parse().unwrap()));
+    assert!("0.0.0.0/0"
+        .parse::<Cidr>()
+        .unwrap()
+        .contains("8.8.8.8".parse().unwrap()));
+    assert_eq!("".parse::<CidrList>(), Ok(CidrList::default()));
+
+    assert!("10.0.0.0/33".parse::<CidrList>().is_err());
+    assert!("10.0.0/8".parse::<CidrList>().is_err());
+    assert!("10.0.0.0/".parse::<CidrList>().is_err());
+    assert!("localhost".parse::<CidrList>().is_err());
+}
+
+#[test]
+fn test_rate_limit_list() {
+    let list: RateLimitList = "/now=100, /offsets = 10,/admin/cache/flush=0"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        list.0,
+        [
+            ("/now".to_owned(), 100),
+            ("/offsets".to_owned(), 10),
+            ("/admin/cache/flush".to_owned(), 0)
+        ]
+    );
+    assert_eq!(
+        serde_json::to_value(&list).unwrap(),
+        serde_json::json!({"/now": 100, "/offsets": 10, "/admin/cache/flush": 0})
+    );
+    assert_eq!("".parse::<RateLimitList>(), Ok(RateLimitList::default()));
+
+    assert!("/now".parse::<RateLimitList>().is_err());
+    assert!("/now=-1".parse::<RateLimitList>().is_err());
+    assert!("/now/batch=10".parse::<RateLimitList>().is_err());
+}
+
+#[test]
+fn test_from_vars() {
+    let config = Config::default();
+    assert_eq!(config.timezones_max_age, DEFAULT_TIMEZONES_MAX_AGE);
+    assert_eq!(config.rate_limit, 0);
+
+    let vars = |max_age: &'static str| {
+        move |name: &str| (name == "TIMEZONES_MAX_AGE").then(|| max_age.to_owned())
+    };
+    let config = Config::from_vars(vars("3600")).unwrap();
+    assert_eq!(config.timezones_max_age, 3600);
+    assert!(Config::from_vars(vars("4294967296")).is_err());
+    assert!(Config::from_vars(vars("1d")).is_err());
+}
+
+#[test]
+fn test_endpoints() {
+    let endpoints = Endpoints {
+        enabled: Some("/now, /health,/admin/cache/flush".parse().unwrap()),
+        disabled: "/admin/cache/flush".parse().unwrap(),
+        debug: false,
+    };
+    assert_eq!(endpoints.active(), ["/health", "/now"]);
+    assert!(!endpoints.is_enabled("/now/bucket"));
+
+    let mut endpoints = Endpoints {
+        enabled: None,
+        disabled: "/metrics".parse().unwrap(),
+        debug: false,
+    };
+    assert!(endpoints.is_enabled("/now/{continent}/{region}"));
+    assert!(!endpoints.is_enabled("/metrics"));
+    // the debug route is off as well
+    assert!(!endpoints.is_enabled("/debug/tz/{continent}/{region}"));
+    assert_eq!(endpoints.active().len(), ROUTE_METHODS.len() - 2);
+    endpoints.debug = true;
+    assert!(endpoints.is_enabled("/debug/tz/{continent}/{region}"));
+
+    assert!("/now,/nope".parse::<EndpointList>().is_err());
+    assert!("/now/Europe/Berlin".parse::<EndpointList>().is_err());
+}
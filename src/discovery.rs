@@ -0,0 +1,209 @@
+//! NTP server list resolved from a service discovery source instead of `NTP_SERVER`
+
+use std::{fmt, net::ToSocketAddrs, str::FromStr, time::Duration};
+
+use actix_web::web;
+use log::{info, warn};
+use thiserror::Error;
+
+use crate::app_context::AppContext;
+
+/// Upper bound for one HTTP discovery request
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(2);
+/// NTP port of the servers resolved from a DNS name without one
+const NTP_PORT: u16 = 123;
+
+#[derive(Debug, Error)]
+pub enum DiscoveryError {
+    #[error("discovery request failed : {0}")]
+    Request(String),
+    #[error("discovery lookup failed : {0}")]
+    Lookup(String),
+    #[error("discovery returned no server")]
+    Empty,
+}
+
+/// Where the current server list comes from
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiscoverySource {
+    /// `GET` answering a JSON array of servers, or one server per line or comma
+    Http(String),
+    /// `dns:<name>[:<port>]`, every address the name resolves to is a server
+    Dns(String, u16),
+}
+
+impl FromStr for DiscoverySource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.starts_with("http://") || s.starts_with("https://") {
+            return Ok(Self::Http(s.to_owned()));
+        }
+        let Some(name) = s.strip_prefix("dns:") else {
+            return Err(format!(
+                "invalid discovery source {s:?}, expected an http(s) URL or dns:<name>"
+            ));
+        };
+        let (host, port) = match name.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse()
+                    .map_err(|_| format!("invalid discovery port {port:?}"))?,
+            ),
+            None => (name, NTP_PORT),
+        };
+        if host.is_empty() {
+            return Err("missing discovery DNS name".to_owned());
+        }
+        Ok(Self::Dns(host.to_owned(), port))
+    }
+}
+
+impl fmt::Display for DiscoverySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Http(url) => f.write_str(url),
+            Self::Dns(host, port) => write!(f, "dns:{host}:{port}"),
+        }
+    }
+}
+
+impl serde::Serialize for DiscoverySource {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Servers of a discovery answer, a JSON array of strings or a plain list
+fn parse_servers(body: &str) -> Result<Vec<String>, DiscoveryError> {
+    let servers: Vec<String> = if body.trim_start().starts_with('[') {
+        serde_json::from_str(body).map_err(|err| DiscoveryError::Request(err.to_string()))?
+    } else {
+        body.split([',', '\n']).map(str::to_owned).collect()
+    };
+    let servers: Vec<_> = servers
+        .iter()
+        .map(|server| server.trim())
+        .filter(|server| !server.is_empty())
+        .map(str::to_owned)
+        .collect();
+    if servers.is_empty() {
+        return Err(DiscoveryError::Empty);
+    }
+    Ok(servers)
+}
+
+impl DiscoverySource {
+    /// Current server list, blocking
+    pub fn resolve(&self) -> Result<Vec<String>, DiscoveryError> {
+        match self {
+            Self::Http(url) => {
+                let body = ureq::get(url)
+                    .timeout(DISCOVERY_TIMEOUT)
+                    .call()
+                    .map_err(|err| DiscoveryError::Request(err.to_string()))?
+                    .into_string()
+                    .map_err(|err| DiscoveryError::Request(err.to_string()))?;
+                parse_servers(&body)
+            }
+            Self::Dns(host, port) => {
+                let mut servers: Vec<_> = (host.as_str(), *port)
+                    .to_socket_addrs()
+                    .map_err(|err| DiscoveryError::Lookup(err.to_string()))?
+                    .map(|addr| addr.to_string())
+                    .collect();
+                servers.dedup();
+                if servers.is_empty() {
+                    return Err(DiscoveryError::Empty);
+                }
+                Ok(servers)
+            }
+        }
+    }
+}
+
+/// Replace the servers of `app` with the discovered ones, or go back to the
+/// `NTP_SERVER` list when the source fails
+pub async fn refresh(app: &AppContext, source: &DiscoverySource) {
+    let lookup = source.clone();
+    let ret = tokio::task::spawn_blocking(move || lookup.resolve())
+        .await
+        .unwrap_or_else(|err| Err(DiscoveryError::Lookup(err.to_string())));
+    match ret {
+        Ok(servers) => {
+            info!(
+                "Discovered NTP servers from {source} : {}",
+                servers.join(", ")
+            );
+            app.set_ntp_servers(servers);
+        }
+        Err(err) => {
+            warn!("NTP discovery from {source} failed, use NTP_SERVER : {err}");
+            app.reset_ntp_servers();
+        }
+    }
+}
+
+/// Refresh the server list every `interval`, the first lookup is done by the
+/// caller at startup
+pub async fn run(app: web::Data<AppContext>, source: DiscoverySource, interval: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+        refresh(&app, &source).await;
+    }
+}
+
+#[test]
+fn test_discovery_source_parse() {
+    assert_eq!(
+        "https://consul/v1/ntp".parse(),
+        Ok(DiscoverySource::Http("https://consul/v1/ntp".to_owned()))
+    );
+    assert_eq!(
+        "dns:ntp.service.consul".parse(),
+        Ok(DiscoverySource::Dns("ntp.service.consul".to_owned(), 123))
+    );
+    assert_eq!(
+        "dns:ntp.local:1123".parse(),
+        Ok(DiscoverySource::Dns("ntp.local".to_owned(), 1123))
+    );
+    assert!("ntp.local".parse::<DiscoverySource>().is_err());
+    assert!("dns:".parse::<DiscoverySource>().is_err());
+    assert!("dns:ntp.local:port".parse::<DiscoverySource>().is_err());
+
+    assert_eq!(
+        parse_servers(r#"["a:123", " b "]"#).unwrap(),
+        vec!["a:123", "b"]
+    );
+    assert_eq!(
+        parse_servers("a:123\nb,c\n").unwrap(),
+        vec!["a:123", "b", "c"]
+    );
+    assert!(matches!(parse_servers("\n"), Err(DiscoveryError::Empty)));
+}
+
+#[tokio::test]
+async fn test_discovery_refresh() {
+    use crate::mock_peer::MockPeer;
+
+    let app = AppContext::new(60, "static:123".to_owned());
+    let discovery = MockPeer::start(serde_json::json!(["one:123", "two:123"]));
+    let source: DiscoverySource = discovery.url().parse().unwrap();
+
+    refresh(&app, &source).await;
+    assert_eq!(*app.ntp_servers(), ["one:123", "two:123"]);
+
+    // a source that stopped answering falls back to NTP_SERVER
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let dead = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+    refresh(&app, &dead.parse().unwrap()).await;
+    assert_eq!(*app.ntp_servers(), ["static:123"]);
+
+    refresh(&app, &"dns:localhost:1123".parse().unwrap()).await;
+    assert!(app
+        .ntp_servers()
+        .iter()
+        .all(|server| server.ends_with(":1123")));
+}
@@ -0,0 +1,194 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, FixedOffset, Offset, TimeDelta, TimeZone, Utc};
+use chrono_tz::{OffsetComponents, OffsetName, Tz};
+
+use crate::{
+    models::{Transition, ZoneOffset},
+    timezone,
+};
+
+/// How far ahead a rule collects the transitions, enough for the default
+/// year long `/transitions` window until the next transition passes
+const RULE_HORIZON_DAYS: i64 = 2 * 366;
+
+/// Offset of a zone over `[since, until)`, `until` being the next transition
+/// or the horizon when the zone has none, and the transitions up to the horizon
+#[derive(Clone, Debug)]
+pub struct ZoneRule {
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    pub horizon: DateTime<Utc>,
+    pub offset: FixedOffset,
+    pub abbreviation: Arc<str>,
+    pub is_dst: bool,
+    upcoming: Arc<[(DateTime<Utc>, Transition)]>,
+}
+
+impl ZoneRule {
+    /// Uncached rule in effect at `at`, scanning the horizon for transitions
+    pub fn compute(tz: &Tz, at: DateTime<Utc>) -> Self {
+        let offset = tz.offset_from_utc_datetime(&at.naive_utc());
+        let horizon = at + TimeDelta::days(RULE_HORIZON_DAYS);
+        let upcoming: Arc<[_]> = timezone::upcoming_transitions(tz, at, horizon).into();
+
+        Self {
+            since: at,
+            until: upcoming.first().map_or(horizon, |(next, _)| *next),
+            horizon,
+            offset: offset.fix(),
+            abbreviation: offset.abbreviation().unwrap_or_default().into(),
+            is_dst: !offset.dst_offset().is_zero(),
+            upcoming,
+        }
+    }
+
+    pub fn covers(&self, at: DateTime<Utc>) -> bool {
+        (self.since..self.until).contains(&at)
+    }
+
+    /// Same as `timezone::zone_offset` for an instant the rule covers
+    pub fn zone_offset(&self, tz: &Tz, at: DateTime<Utc>) -> ZoneOffset {
+        ZoneOffset {
+            zone: tz.name().to_owned(),
+            at: at.to_rfc3339(),
+            offset: self.offset.to_string(),
+            offset_seconds: self.offset.local_minus_utc(),
+            abbreviation: self.abbreviation.to_string(),
+            is_dst: self.is_dst,
+            alias: timezone::alias(tz),
+        }
+    }
+
+    /// Same as `timezone::transitions` from an instant the rule covers, only
+    /// the part of the window past the horizon is scanned
+    pub fn transitions(&self, tz: &Tz, end: DateTime<Utc>) -> Vec<Transition> {
+        let mut ret: Vec<_> = self
+            .upcoming
+            .iter()
+            .take_while(|(at, _)| *at <= end)
+            .map(|(_, transition)| transition.clone())
+            .collect();
+        if end > self.horizon {
+            ret.extend(timezone::transitions(tz, self.horizon, end));
+        }
+        ret
+    }
+}
+
+/// Per zone memoization of the rule in effect now, computed on the first
+/// lookup and replaced once its next transition passes. Lookups of instants
+/// before the cached rule are computed fresh and not cached
+pub struct DstCache {
+    enabled: bool,
+    rules: Mutex<HashMap<Tz, ZoneRule>>,
+}
+
+impl DstCache {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            rules: Mutex::default(),
+        }
+    }
+
+    pub fn rule(&self, tz: Tz, at: DateTime<Utc>) -> ZoneRule {
+        if !self.enabled {
+            return ZoneRule::compute(&tz, at);
+        }
+
+        let cached = self.rules.lock().unwrap().get(&tz).cloned();
+        match cached {
+            Some(rule) if rule.covers(at) => rule,
+            Some(rule) if at < rule.since => ZoneRule::compute(&tz, at),
+            _ => {
+                // computed outside the lock, the scan takes a few hundred lookups
+                let rule = ZoneRule::compute(&tz, at);
+                self.rules.lock().unwrap().insert(tz, rule.clone());
+                rule
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for DstCache {
+    fn default() -> Self {
+        Self::new(true)
+    }
+}
+
+#[test]
+fn test_dst_cache_matches_fresh() {
+    let cache = DstCache::default();
+    let tz = chrono_tz::Europe::London;
+    let start = Utc.with_ymd_and_hms(2026, 3, 1, 0, 0, 0).unwrap();
+
+    fn json(value: impl serde::Serialize) -> serde_json::Value {
+        serde_json::to_value(value).unwrap()
+    }
+
+    // every 13 hours through the spring forward and the autumn fall back
+    for hours in (0..24 * 270).step_by(13) {
+        let at = start + TimeDelta::hours(hours);
+        let rule = cache.rule(tz, at);
+        assert_eq!(
+            json(rule.zone_offset(&tz, at)),
+            json(timezone::zone_offset(&tz, at)),
+            "{at}"
+        );
+        for days in [1, 365, 3650] {
+            let end = at + TimeDelta::days(days);
+            assert_eq!(
+                json(rule.transitions(&tz, end)),
+                json(timezone::transitions(&tz, at, end)),
+                "{at} {days}"
+            );
+        }
+    }
+    assert_eq!(cache.len(), 1);
+}
+
+#[test]
+fn test_dst_cache_invalidation() {
+    let cache = DstCache::default();
+    let tz = chrono_tz::Europe::London;
+    let winter = Utc.with_ymd_and_hms(2026, 3, 1, 0, 0, 0).unwrap();
+
+    let rule = cache.rule(tz, winter);
+    let transition = Utc.with_ymd_and_hms(2026, 3, 29, 1, 0, 0).unwrap();
+    assert_eq!(rule.until, transition);
+    assert_eq!(&*rule.abbreviation, "GMT");
+
+    let before = transition - TimeDelta::seconds(1);
+    assert_eq!(cache.rule(tz, before).since, winter);
+
+    let summer = cache.rule(tz, transition);
+    assert_eq!(summer.since, transition);
+    assert_eq!(&*summer.abbreviation, "BST");
+    assert!(summer.is_dst);
+
+    // historical lookups leave the current rule cached
+    assert_eq!(&*cache.rule(tz, winter).abbreviation, "GMT");
+    assert_eq!(cache.rule(tz, transition).since, transition);
+
+    let fixed = cache.rule(chrono_tz::Asia::Tokyo, winter);
+    assert_eq!(fixed.until, winter + TimeDelta::days(RULE_HORIZON_DAYS));
+    assert!(fixed
+        .transitions(&chrono_tz::Asia::Tokyo, fixed.until)
+        .is_empty());
+
+    let disabled = DstCache::new(false);
+    assert_eq!(disabled.rule(tz, transition).offset, summer.offset);
+    assert!(disabled.is_empty());
+}
@@ -0,0 +1,142 @@
+//! Leap second announcements and optional smearing.
+//!
+//! NTP announces a leap second during the UTC day it happens at the end of.
+//! Without smearing the served clock steps by a second once the next sync
+//! picks up the corrected time. With smearing the second is spread linearly
+//! over a window centered on the leap, so the clock never steps but it
+//! differs from true UTC by up to half a second inside the window and
+//! disagrees with non smearing sources meanwhile.
+
+use chrono::{DateTime, Days, NaiveTime, TimeDelta, Utc};
+use serde_derive::Serialize;
+
+/// Default smear window, a full day from noon to noon around the leap
+pub const DEFAULT_SMEAR_WINDOW: u64 = 24 * 60 * 60; // sec
+
+/// The NTP leap indicator of the last sync
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub enum LeapIndicator {
+    #[default]
+    #[serde(rename = "none")]
+    None,
+    /// The last minute of the day has 61 seconds
+    #[serde(rename = "add")]
+    Insert,
+    /// The last minute of the day has 59 seconds
+    #[serde(rename = "sub")]
+    Delete,
+    /// The server clock is not synchronized
+    #[serde(rename = "unsync")]
+    Alarm,
+}
+
+impl From<ntp::formats::LeapIndicator> for LeapIndicator {
+    fn from(li: ntp::formats::LeapIndicator) -> Self {
+        match li {
+            ntp::formats::LeapIndicator::NoWarning => Self::None,
+            ntp::formats::LeapIndicator::AddOne => Self::Insert,
+            ntp::formats::LeapIndicator::SubOne => Self::Delete,
+            ntp::formats::LeapIndicator::Unknown => Self::Alarm,
+        }
+    }
+}
+
+/// A leap second announced by a sync
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScheduledLeap {
+    /// Midnight UTC at which the leap second happens
+    pub at: DateTime<Utc>,
+    /// `1` for an inserted second, `-1` for a deleted one
+    sign: i64,
+}
+
+impl ScheduledLeap {
+    /// Leap announced at `time` by `leap`, due at the end of that UTC day
+    pub fn announced(leap: LeapIndicator, time: DateTime<Utc>) -> Option<Self> {
+        let sign = match leap {
+            LeapIndicator::Insert => 1,
+            LeapIndicator::Delete => -1,
+            LeapIndicator::None | LeapIndicator::Alarm => return None,
+        };
+        let at = time
+            .date_naive()
+            .checked_add_days(Days::new(1))?
+            .and_time(NaiveTime::MIN)
+            .and_utc();
+        Some(Self { at, sign })
+    }
+
+    /// Smeared value of `time` interpolated from a sync taken before the leap
+    /// when `synced_before` or after it otherwise
+    pub fn smear(
+        &self,
+        time: DateTime<Utc>,
+        window: TimeDelta,
+        synced_before: bool,
+    ) -> DateTime<Utc> {
+        let start = self.at - window / 2;
+        let window_ns = window.num_nanoseconds().unwrap_or(i64::MAX).max(1);
+        let elapsed_ns = (time - start)
+            .num_nanoseconds()
+            .unwrap_or(i64::MAX)
+            .clamp(0, window_ns);
+        // nanoseconds of the leap second already absorbed by the smear
+        let absorbed = (i128::from(elapsed_ns) * 1_000_000_000 / i128::from(window_ns)) as i64;
+
+        // a sync before the leap doesn't include it yet, one after already stepped
+        let shift = if synced_before {
+            -absorbed
+        } else {
+            1_000_000_000 - absorbed
+        };
+        time + TimeDelta::nanoseconds(self.sign * shift)
+    }
+}
+
+#[test]
+fn test_scheduled_leap() {
+    use chrono::TimeZone;
+
+    let time = Utc.with_ymd_and_hms(2016, 12, 31, 10, 0, 0).unwrap();
+    assert_eq!(ScheduledLeap::announced(LeapIndicator::None, time), None);
+    assert_eq!(ScheduledLeap::announced(LeapIndicator::Alarm, time), None);
+    assert_eq!(
+        ScheduledLeap::announced(LeapIndicator::Insert, time).map(|leap| leap.at),
+        Some(Utc.with_ymd_and_hms(2017, 1, 1, 0, 0, 0).unwrap())
+    );
+}
+
+#[test]
+fn test_smear() {
+    use chrono::TimeZone;
+
+    let window = TimeDelta::hours(24);
+    let at = Utc.with_ymd_and_hms(2017, 1, 1, 0, 0, 0).unwrap();
+    let leap = ScheduledLeap::announced(LeapIndicator::Insert, at - TimeDelta::hours(1)).unwrap();
+
+    // untouched before the window, half a second behind at the leap
+    let before = at - TimeDelta::hours(13);
+    assert_eq!(leap.smear(before, window, true), before);
+    assert_eq!(
+        leap.smear(at, window, true),
+        at - TimeDelta::milliseconds(500)
+    );
+    // a sync after the leap is a second behind the pre leap count
+    assert_eq!(
+        leap.smear(at, window, false),
+        at + TimeDelta::milliseconds(500)
+    );
+    // the whole second is absorbed at the end of the window
+    let after = at + TimeDelta::hours(12);
+    assert_eq!(
+        leap.smear(after, window, true),
+        after - TimeDelta::seconds(1)
+    );
+    assert_eq!(leap.smear(after, window, false), after);
+
+    let leap = ScheduledLeap::announced(LeapIndicator::Delete, at - TimeDelta::hours(1)).unwrap();
+    assert_eq!(
+        leap.smear(at, window, true),
+        at + TimeDelta::milliseconds(500)
+    );
+}
@@ -0,0 +1,30 @@
+pub mod affinity;
+pub mod app_context;
+pub mod astro;
+pub mod config;
+pub mod discovery;
+pub mod dst_cache;
+pub mod leap;
+pub mod listener;
+pub mod metrics;
+pub mod middleware;
+#[cfg(test)]
+mod mock_ntp;
+#[cfg(test)]
+mod mock_peer;
+pub mod models;
+pub mod ntp_error;
+pub mod peer;
+pub mod posix_tz;
+pub mod precompressed;
+pub mod retry_budget;
+pub mod routes;
+pub mod self_check;
+pub mod sntp;
+pub mod time_scale;
+pub mod timezone;
+pub mod tls;
+pub mod tz_database;
+pub mod utils;
+pub mod watchdog;
+pub mod zone_cache;
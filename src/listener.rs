@@ -0,0 +1,61 @@
+//! Binding of the listening sockets with an actionable error when the port is
+//! taken, and optional retries for fast restarts
+
+use std::{io, net::TcpListener, time::Duration};
+
+use color_eyre::eyre::eyre;
+use log::{info, warn};
+
+/// Attempts after the first one with `PORT_RETRY`, the backoff doubles from
+/// `FIRST_BACKOFF` so the last attempt happens about 6s after the first
+const RETRY_ATTEMPTS: u32 = 5;
+const FIRST_BACKOFF: Duration = Duration::from_millis(200);
+
+/// Bind `address`, when it is in use and `retry` is set try again with backoff
+pub async fn bind(address: &str, retry: bool) -> color_eyre::Result<TcpListener> {
+    let attempts = if retry { RETRY_ATTEMPTS + 1 } else { 1 };
+    let mut backoff = FIRST_BACKOFF;
+
+    for attempt in 1..=attempts {
+        info!("Binding {address} (attempt {attempt}/{attempts})");
+        match TcpListener::bind(address) {
+            Ok(listener) => return Ok(listener),
+            Err(err) if err.kind() == io::ErrorKind::AddrInUse && attempt < attempts => {
+                warn!("{address} is in use, retrying in {backoff:?}");
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+            Err(err) if err.kind() == io::ErrorKind::AddrInUse => {
+                return Err(eyre!(
+                    "{address} is already in use by another process: stop it, pick another PORT/BIND, \
+                     or set PORT_RETRY=true to wait for a restarting instance to release it"
+                ));
+            }
+            Err(err) => return Err(eyre!("Cannot bind {address} : {err}")),
+        }
+    }
+    unreachable!("the last attempt always returns")
+}
+
+#[tokio::test]
+async fn test_bind_port_in_use() {
+    let occupied = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = occupied.local_addr().unwrap().to_string();
+
+    let err = bind(&address, false).await.unwrap_err();
+    assert!(err.to_string().contains("already in use"), "{err}");
+
+    // released while retrying
+    let release = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(300));
+        drop(occupied);
+    });
+    assert!(bind(&address, true).await.is_ok());
+    release.join().unwrap();
+
+    assert!(bind("256.0.0.1:80", false)
+        .await
+        .unwrap_err()
+        .to_string()
+        .starts_with("Cannot bind"));
+}
@@ -1,71 +1,19 @@
-mod app_context;
-mod models;
-mod utils;
-
 use actix_cors::Cors;
-use actix_web::{error, get, web, App, HttpResponse, HttpServer, Responder, Result};
-use app_context::AppContext;
-use chrono::TimeZone;
-use chrono_tz::Tz;
-use log::{info, LevelFilter};
-use simplelog::{ColorChoice, Config, TermLogger, TerminalMode};
-use std::fmt::Debug;
-use std::{env, str::FromStr};
-
-const DEFAULT_IP_ADDRESS: &str = "127.0.0.1";
-const DEFAULT_NTP_SERVER: &str = "time.google.com:123";
-const DEFAULT_PORT: u16 = 3000;
-const DEFAULT_CACHE_DURATION: u64 = 5 * 60; // sec
-const DEFAULT_CORS_ORIGIN: &str = "127.0.0.1";
-
-#[get("/health")]
-async fn health() -> impl Responder {
-    HttpResponse::Ok()
-}
-
-#[get("/now")]
-async fn now(app: web::Data<AppContext>) -> Result<impl Responder> {
-    let time = app.get_time().await;
-    info!("/now: {:?}", time);
-    Ok(web::Json(models::Time::from(time)))
-}
-
-#[get("/now/{continent}/{region}")]
-async fn now_with_tz(
-    args: web::Path<models::TimeZone>,
-    app: web::Data<AppContext>,
-) -> Result<impl Responder> {
-    let timezone: Tz = format!(
-        "{cont}/{region}",
-        cont = utils::to_camel_case(&args.continent),
-        region = utils::to_camel_case(&args.region)
-    )
-    .parse()
-    .map_err(|_| error::ErrorBadRequest("Invalid Time zone"))?;
-
-    let time = app.get_time().await;
-
-    let time = timezone.from_utc_datetime(&time.naive_utc());
-    info!("now with tz: {:?}", time);
-    Ok(web::Json(models::Time::from(time)))
-}
-
-#[inline]
-fn parse_env<T>(name: &str, default: T) -> T
-where
-    T: FromStr + Debug,
-{
-    env::var(name)
-        .ok()
-        .and_then(|val| {
-            println!("Get the {name} value from env:{val:?}");
-            val.parse::<T>().ok()
-        })
-        .unwrap_or_else(|| {
-            println!("Get the default value fro {name} :{default:?}");
-            default
-        })
-}
+use actix_web::http::header;
+use actix_web::middleware::{from_fn, Compress};
+use actix_web::{web, App, HttpServer};
+use color_eyre::eyre::eyre;
+use log::{info, warn};
+use simplelog::{ColorChoice, TermLogger, TerminalMode};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use timeapi::app_context::AppContext;
+use timeapi::config::Config;
+use timeapi::tz_database::TzDatabase;
+use timeapi::{discovery, listener, middleware, routes, self_check, tls, watchdog};
+use tokio::sync::Semaphore;
 
 #[actix_web::main]
 async fn main() -> color_eyre::Result<()> {
@@ -73,40 +21,219 @@ async fn main() -> color_eyre::Result<()> {
 
     let _ = dotenvy::dotenv()?;
 
-    let loglevel = parse_env("LOG_LEVEL", LevelFilter::Info);
-    let address = parse_env("IP", DEFAULT_IP_ADDRESS.to_owned());
-    let port = parse_env("PORT", DEFAULT_PORT);
-    let ntp_server = parse_env("NTP_SERVER", DEFAULT_NTP_SERVER.to_owned());
-    let cache_timeout = parse_env("CACHE_TIMEOUT", DEFAULT_CACHE_DURATION);
-    let cors_origin = parse_env("CORS_ORIGIN", DEFAULT_CORS_ORIGIN.to_owned());
+    let config = Config::from_env()?;
 
     TermLogger::init(
-        loglevel,
-        Config::default(),
+        config.log_level,
+        simplelog::Config::default(),
         TerminalMode::Mixed,
         ColorChoice::Auto,
     )?;
 
-    let app_state = web::Data::new(AppContext::new(cache_timeout, ntp_server));
-
-    info!("Listening on {address}:{port}");
-
-    HttpServer::new(move || {
+    if !config.cpu_affinity.0.is_empty() {
+        match config.cpu_affinity.apply() {
+            Ok(()) => info!("Pinned to cores {:?}", config.cpu_affinity.0),
+            Err(err) => warn!(
+                "Cannot set CPU_AFFINITY {:?} : {err}",
+                config.cpu_affinity.0
+            ),
+        }
+    }
+
+    config.field_names.clone().install();
+    config.default_precision.install_default();
+
+    if config.self_check || std::env::args().any(|arg| arg == "--check") {
+        let checks = self_check::run(&self_check::check_context(&config)).await;
+        std::process::exit(if self_check::report(&checks) { 0 } else { 1 });
+    }
+
+    let tz_database = match &config.tzdata_dir {
+        Some(dir) => TzDatabase::load(Path::new(dir))
+            .map_err(|err| eyre!("Cannot load TZDATA_DIR {dir} : {err}"))?,
+        None => TzDatabase::default(),
+    };
+    tz_database.log_active();
+
+    let app_state = web::Data::new(
+        AppContext::new(config.cache_timeout, config.ntp_server.clone())
+            .with_ready_probe(config.ready_probe)
+            .with_peer(config.peer_url.clone())
+            .with_sanity_window(config.sanity_window_days)
+            .with_leap_smear(config.leap_smear.then_some(config.leap_smear_window))
+            .with_rounding(config.rounding)
+            .with_min_sync_interval(Duration::from_secs(config.min_sync_interval))
+            .with_min_poll_interval(Duration::from_secs(config.min_poll_interval))
+            .with_ntp_version(config.ntp_version)
+            .with_retry_budget(config.ntp_retry_budget)
+            .with_max_step(config.max_step_ms)
+            .with_clock_jump(config.clock_jump_ms)
+            .with_max_stratum(config.max_stratum)
+            .with_persist_max_age(config.cache_persist_max_age)
+            .with_fail_on_degraded(config.fail_on_degraded)
+            .with_strict_rest(config.strict_rest)
+            .with_warmup_wait(Duration::from_millis(config.warmup_wait_ms))
+            .with_dst_cache(config.dst_cache)
+            .with_tz_database(tz_database),
+    );
+
+    let cache_persist_path = config.cache_persist_path.clone().map(PathBuf::from);
+    if let Some(path) = &cache_persist_path {
+        if let Err(err) = app_state.load_cache(path).await {
+            warn!("Cannot load the cache from {} : {err}", path.display());
+        }
+    }
+
+    if let Some(source) = config.ntp_discovery.clone() {
+        discovery::refresh(&app_state, &source).await;
+        if config.ntp_discovery_interval > 0 {
+            let interval = Duration::from_secs(config.ntp_discovery_interval);
+            actix_web::rt::spawn(discovery::run(app_state.clone(), source, interval));
+        }
+    }
+
+    if config.watchdog {
+        actix_web::rt::spawn(watchdog::run(app_state.clone()));
+    }
+
+    let listeners = config.listeners();
+    let mut admin_listeners = Vec::new();
+    for listener in listeners.iter().filter(|listener| listener.admin) {
+        admin_listeners.extend(listener.address.to_socket_addrs()?);
+    }
+    let admin_listeners: Arc<[SocketAddr]> = admin_listeners.into();
+    let admin_token: Option<Arc<str>> = config.admin_token.as_deref().map(Into::into);
+    if admin_token.is_none() {
+        warn!("ADMIN_TOKEN is unset, the /admin routes are disabled");
+    }
+    let trust_proxy = config.trust_proxy;
+    let slow_request =
+        (config.slow_request_ms > 0).then(|| Duration::from_millis(config.slow_request_ms));
+    let port_retry = config.port_retry;
+    let compression_min_bytes = config.compression_min_bytes;
+    let concurrency_limit =
+        (config.max_concurrent > 0).then(|| Arc::new(Semaphore::new(config.max_concurrent)));
+    let allowed_hosts: Arc<[String]> = config.allowed_hosts.clone().into();
+    let ip_acl = Arc::new(middleware::IpAcl {
+        allow: config.allow_ips.clone(),
+        deny: config.deny_ips.clone(),
+    });
+    if !ip_acl.is_empty() {
+        info!(
+            "IP access list: {} allowed, {} denied networks",
+            ip_acl.allow.0.len(),
+            ip_acl.deny.0.len()
+        );
+    }
+    let rate_limiter = Arc::new(middleware::RateLimiter::new(
+        config.rate_limit,
+        &config.rate_limits,
+    ));
+    if !rate_limiter.is_empty() {
+        info!(
+            "Rate limits: {} per client and minute by default, {} endpoint limits",
+            config.rate_limit,
+            config.rate_limits.0.len()
+        );
+    }
+    let tls_config = match (&config.tls_cert, &config.tls_key) {
+        (Some(cert), Some(key)) => {
+            Some(tls::load_server_config(cert, key, config.tls_min_version)?)
+        }
+        (None, None) => None,
+        _ => color_eyre::eyre::bail!("TLS_CERT and TLS_KEY must be set together"),
+    };
+    let max_body_bytes = config.max_body_bytes;
+    info!("Endpoints: {}", config.endpoints.active().join(", "));
+    let endpoints = web::Data::new(config.endpoints.clone());
+    let config = web::Data::new(config);
+    let metrics = app_state.metrics().clone();
+    let shutdown_state = app_state.clone();
+
+    let server = HttpServer::new(move || {
         let cors = Cors::default()
-            .allowed_origin(cors_origin.clone().as_str())
-            .allowed_methods(vec!["GET"])
+            .allowed_origin(config.cors_origin.as_str())
+            // POST for the JSON body of /offsets
+            .allowed_methods(vec!["GET", "POST"])
+            .allowed_header(header::CONTENT_TYPE)
             .max_age(3600);
 
+        let (payload_config, json_config) = routes::body_limits(max_body_bytes);
         App::new()
             .app_data(app_state.clone())
+            .app_data(config.clone())
+            .app_data(endpoints.clone())
+            .app_data(payload_config)
+            .app_data(json_config)
+            .wrap(from_fn(move |req, next| {
+                middleware::compression_threshold(req, next, compression_min_bytes)
+            }))
+            .wrap(Compress::default())
+            .wrap(from_fn(middleware::strip_identity_encoding))
             .wrap(cors)
-            .service(now)
-            .service(health)
-            .service(now_with_tz)
+            .wrap(from_fn({
+                let allowed_hosts = allowed_hosts.clone();
+                move |req, next| middleware::allowed_hosts(req, next, allowed_hosts.clone())
+            }))
+            .wrap(from_fn({
+                let concurrency_limit = concurrency_limit.clone();
+                move |req, next| middleware::concurrency_limit(req, next, concurrency_limit.clone())
+            }))
+            .wrap(from_fn({
+                let rate_limiter = rate_limiter.clone();
+                move |req, next| middleware::rate_limit(req, next, rate_limiter.clone())
+            }))
+            .wrap(from_fn({
+                let ip_acl = ip_acl.clone();
+                move |req, next| middleware::ip_acl(req, next, ip_acl.clone())
+            }))
+            .wrap(from_fn(move |req, next| {
+                middleware::client_ip(req, next, trust_proxy, slow_request)
+            }))
+            .configure(|cfg| routes::configure(cfg, &endpoints))
+            .service(
+                web::scope("/admin")
+                    .wrap(from_fn({
+                        let admin_token = admin_token.clone();
+                        move |req, next| middleware::admin_token(req, next, admin_token.clone())
+                    }))
+                    .wrap(from_fn({
+                        let admin_listeners = admin_listeners.clone();
+                        move |req, next| {
+                            middleware::admin_listener_only(req, next, admin_listeners.clone())
+                        }
+                    }))
+                    .configure(|cfg| routes::configure_admin(cfg, &endpoints)),
+            )
+            .default_service(web::to(routes::fallback))
     })
-    .bind((address, port))?
-    .run()
-    .await?;
+    .on_connect(move |_, ext| {
+        ext.insert(metrics.connect());
+    });
+
+    let mut server = server;
+    for listener in &listeners {
+        let (address, admin) = (listener.address.as_str(), listener.admin);
+        let socket = listener::bind(address, port_retry).await?;
+        server = match &tls_config {
+            Some(tls_config) => {
+                info!("Listening on https://{address} (admin: {admin})");
+                server.listen_rustls_0_23(socket, tls_config.clone())?
+            }
+            None => {
+                info!("Listening on {address} (admin: {admin})");
+                server.listen(socket)?
+            }
+        };
+    }
+
+    server.run().await?;
+
+    if let Some(path) = &cache_persist_path {
+        if let Err(err) = shutdown_state.save_cache(path).await {
+            warn!("Cannot save the cache to {} : {err}", path.display());
+        }
+    }
 
     Ok(())
 }
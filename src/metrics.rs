@@ -0,0 +1,246 @@
+//! Process wide counters served by `/metrics` in the Prometheus text format
+
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use chrono_tz::Tz;
+
+/// Upper bounds in seconds of the served sample age buckets, from sub-second
+/// to well past the default 5 minutes `CACHE_TIMEOUT`
+const SAMPLE_AGE_BUCKETS: [f64; 12] = [
+    0.1, 0.5, 1.0, 5.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1800.0, 3600.0,
+];
+
+/// Cumulative histogram like Prometheus, the last count is `+Inf`
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [AtomicU64; SAMPLE_AGE_BUCKETS.len() + 1],
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    fn observe(&self, value: Duration) {
+        let seconds = value.as_secs_f64();
+        let bucket = SAMPLE_AGE_BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(SAMPLE_AGE_BUCKETS.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(value.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn render(&self, out: &mut String, name: &str) {
+        let mut count = 0;
+        for (index, bucket) in self.buckets.iter().enumerate() {
+            count += bucket.load(Ordering::Relaxed);
+            let bound = SAMPLE_AGE_BUCKETS
+                .get(index)
+                .map_or_else(|| "+Inf".to_owned(), f64::to_string);
+            let _ = writeln!(out, "{name}_bucket{{le=\"{bound}\"}} {count}");
+        }
+        let sum = self.sum_micros.load(Ordering::Relaxed) as f64 / 1e6;
+        let _ = writeln!(out, "{name}_sum {sum}");
+        let _ = writeln!(out, "{name}_count {count}");
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    connections_active: AtomicU64,
+    connections_accepted: AtomicU64,
+    clock_steps: AtomicU64,
+    sample_age: Histogram,
+    /// Requests per zone, capped by the few hundred zones of chrono-tz
+    zones: Mutex<HashMap<Tz, u64>>,
+}
+
+/// Held in the connection extensions, dropped with the connection whatever
+/// closes it, so long lived streams are released too
+pub struct ConnectionGuard(Arc<Metrics>);
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.0.connections_active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Metrics {
+    /// Count a newly accepted connection until the guard is dropped
+    pub fn connect(self: &Arc<Self>) -> ConnectionGuard {
+        self.connections_accepted.fetch_add(1, Ordering::Relaxed);
+        self.connections_active.fetch_add(1, Ordering::Relaxed);
+        ConnectionGuard(self.clone())
+    }
+
+    pub fn connections_active(&self) -> u64 {
+        self.connections_active.load(Ordering::Relaxed)
+    }
+
+    pub fn connections_accepted(&self) -> u64 {
+        self.connections_accepted.load(Ordering::Relaxed)
+    }
+
+    /// Count a sync that moved the time by more than `MAX_STEP_MS`
+    pub fn record_clock_step(&self) {
+        self.clock_steps.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn clock_steps(&self) -> u64 {
+        self.clock_steps.load(Ordering::Relaxed)
+    }
+
+    /// Record the time since the NTP sync a served time is interpolated from,
+    /// system clock and peer times have none and aren't recorded
+    pub fn observe_sample_age(&self, age: Option<Duration>) {
+        if let Some(age) = age {
+            self.sample_age.observe(age);
+        }
+    }
+
+    /// Count a request for `tz`, unknown zones never get here
+    pub fn record_zone(&self, tz: Tz) {
+        *self.zones.lock().unwrap().entry(tz).or_default() += 1;
+    }
+
+    pub fn distinct_zones(&self) -> usize {
+        self.zones.lock().unwrap().len()
+    }
+
+    /// The `n` most requested zones with their counts, ties by name
+    pub fn top_zones(&self, n: usize) -> Vec<(Tz, u64)> {
+        let mut zones: Vec<_> = self
+            .zones
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(tz, count)| (*tz, *count))
+            .collect();
+        zones.sort_by(|(a, a_count), (b, b_count)| {
+            b_count.cmp(a_count).then_with(|| a.name().cmp(b.name()))
+        });
+        zones.truncate(n);
+        zones
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "# HELP timeapi_connections_active Open client connections"
+        );
+        let _ = writeln!(out, "# TYPE timeapi_connections_active gauge");
+        let _ = writeln!(
+            out,
+            "timeapi_connections_active {}",
+            self.connections_active()
+        );
+        let _ = writeln!(
+            out,
+            "# HELP timeapi_connections_accepted_total Accepted client connections"
+        );
+        let _ = writeln!(out, "# TYPE timeapi_connections_accepted_total counter");
+        let _ = writeln!(
+            out,
+            "timeapi_connections_accepted_total {}",
+            self.connections_accepted()
+        );
+        let _ = writeln!(
+            out,
+            "# HELP timeapi_clock_steps_total NTP syncs that moved the time by more than MAX_STEP_MS"
+        );
+        let _ = writeln!(out, "# TYPE timeapi_clock_steps_total counter");
+        let _ = writeln!(out, "timeapi_clock_steps_total {}", self.clock_steps());
+        let _ = writeln!(
+            out,
+            "# HELP timeapi_served_sample_age_seconds Time since the NTP sync of the served time"
+        );
+        let _ = writeln!(out, "# TYPE timeapi_served_sample_age_seconds histogram");
+        self.sample_age
+            .render(&mut out, "timeapi_served_sample_age_seconds");
+        let _ = writeln!(
+            out,
+            "# HELP timeapi_requested_zones Distinct zones requested since startup"
+        );
+        let _ = writeln!(out, "# TYPE timeapi_requested_zones gauge");
+        let _ = writeln!(out, "timeapi_requested_zones {}", self.distinct_zones());
+        out
+    }
+}
+
+#[test]
+fn test_connection_gauge() {
+    let metrics = Arc::new(Metrics::default());
+    let first = metrics.connect();
+    let second = metrics.connect();
+    assert_eq!(metrics.connections_active(), 2);
+
+    drop(first);
+    assert_eq!(metrics.connections_active(), 1);
+    drop(second);
+    assert_eq!(metrics.connections_active(), 0);
+    assert_eq!(metrics.connections_accepted(), 2);
+    assert!(metrics
+        .render()
+        .contains("timeapi_connections_accepted_total 2\n"));
+}
+
+#[test]
+fn test_sample_age_histogram() {
+    let metrics = Metrics::default();
+    metrics.observe_sample_age(Some(Duration::from_millis(50)));
+    metrics.observe_sample_age(Some(Duration::from_secs(42)));
+    metrics.observe_sample_age(Some(Duration::from_secs(7200)));
+    metrics.observe_sample_age(None);
+
+    let out = metrics.render();
+    for line in [
+        "timeapi_served_sample_age_seconds_bucket{le=\"0.1\"} 1\n",
+        "timeapi_served_sample_age_seconds_bucket{le=\"30\"} 1\n",
+        "timeapi_served_sample_age_seconds_bucket{le=\"60\"} 2\n",
+        "timeapi_served_sample_age_seconds_bucket{le=\"3600\"} 2\n",
+        "timeapi_served_sample_age_seconds_bucket{le=\"+Inf\"} 3\n",
+        "timeapi_served_sample_age_seconds_sum 7242.05\n",
+        "timeapi_served_sample_age_seconds_count 3\n",
+    ] {
+        assert!(out.contains(line), "{line}");
+    }
+}
+
+#[test]
+fn test_requested_zones() {
+    let metrics = Metrics::default();
+    assert!(metrics.render().contains("timeapi_requested_zones 0\n"));
+
+    for tz in [
+        Tz::Europe__Berlin,
+        Tz::Asia__Tokyo,
+        Tz::Europe__Berlin,
+        Tz::UTC,
+    ] {
+        metrics.record_zone(tz);
+    }
+    assert_eq!(metrics.distinct_zones(), 3);
+    metrics.record_zone(Tz::Asia__Tokyo);
+    assert_eq!(metrics.distinct_zones(), 3);
+    metrics.record_zone(Tz::America__New_York);
+    assert_eq!(metrics.distinct_zones(), 4);
+    assert!(metrics.render().contains("timeapi_requested_zones 4\n"));
+
+    assert_eq!(
+        metrics.top_zones(3),
+        [
+            (Tz::Asia__Tokyo, 2),
+            (Tz::Europe__Berlin, 2),
+            (Tz::America__New_York, 1)
+        ]
+    );
+    assert_eq!(metrics.top_zones(10).len(), 4);
+}
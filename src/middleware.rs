@@ -0,0 +1,813 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use actix_web::{
+    body::{BodySize, EitherBody, MessageBody},
+    dev::{ResourceDef, ServiceRequest, ServiceResponse},
+    http::header::{self, HeaderValue},
+    middleware::Next,
+    Error, HttpMessage, HttpResponse,
+};
+use log::{info, log, warn, Level};
+use tokio::sync::Semaphore;
+
+use crate::{
+    config::{CidrList, RateLimitList},
+    models::ErrorResponse,
+};
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Client address resolved once per request and stored in the request extensions
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+/// Resolve the client address, the left-most `X-Forwarded-For` entry is only
+/// honored behind a trusted proxy, otherwise anyone could spoof it
+pub fn resolve_client_ip(req: &ServiceRequest, trust_proxy: bool) -> Option<IpAddr> {
+    let forwarded = if trust_proxy {
+        req.headers()
+            .get(X_FORWARDED_FOR)
+            .and_then(|val| val.to_str().ok())
+            .and_then(|val| val.split(',').next())
+            .and_then(|val| val.trim().parse().ok())
+    } else {
+        None
+    };
+
+    forwarded.or_else(|| req.peer_addr().map(|addr| addr.ip()))
+}
+
+/// Access log level, with `SLOW_REQUEST_MS` only the slow requests are above debug
+fn access_log_level(elapsed: Duration, slow_request: Option<Duration>) -> Level {
+    match slow_request {
+        Some(threshold) if elapsed >= threshold => Level::Warn,
+        Some(_) => Level::Debug,
+        None => Level::Info,
+    }
+}
+
+pub async fn client_ip(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+    trust_proxy: bool,
+    slow_request: Option<Duration>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let ip = resolve_client_ip(&req, trust_proxy);
+    req.extensions_mut().insert(ClientIp(ip));
+
+    let method = req.method().clone();
+    let path = req.path().to_owned();
+    let start = Instant::now();
+    let res = next.call(req).await?;
+    let elapsed = start.elapsed();
+
+    let client = ip.map_or_else(|| "-".to_owned(), |ip| ip.to_string());
+    log!(
+        access_log_level(elapsed, slow_request),
+        "{client} {method} {path} {status} {elapsed:?}",
+        status = res.status()
+    );
+    Ok(res)
+}
+
+/// Mark bodies smaller than `min_bytes` as `identity` so the outer `Compress`
+/// leaves them alone, must be wrapped inside `Compress`
+pub async fn compression_threshold(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+    min_bytes: u64,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let mut res = next.call(req).await?;
+    let small = matches!(res.response().body().size(), BodySize::Sized(len) if len < min_bytes);
+    if small && !res.headers().contains_key(header::CONTENT_ENCODING) {
+        res.headers_mut().insert(
+            header::CONTENT_ENCODING,
+            HeaderValue::from_static("identity"),
+        );
+    }
+    Ok(res)
+}
+
+/// Drop the `identity` marker of `compression_threshold` before it reaches the
+/// client, must be wrapped outside `Compress`
+pub async fn strip_identity_encoding(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let mut res = next.call(req).await?;
+    if res
+        .headers()
+        .get(header::CONTENT_ENCODING)
+        .is_some_and(|val| val == "identity")
+    {
+        res.headers_mut().remove(header::CONTENT_ENCODING);
+    }
+    Ok(res)
+}
+
+/// Whether the `Host` is on the list, entries without a port match any port
+fn host_allowed(host: &str, allowed: &[String]) -> bool {
+    let name = match host.rsplit_once(':') {
+        // keep IPv6 literals such as `[::1]` whole
+        Some((name, port)) if !port.contains(']') => name,
+        _ => host,
+    };
+    allowed
+        .iter()
+        .any(|entry| entry.eq_ignore_ascii_case(host) || entry.eq_ignore_ascii_case(name))
+}
+
+/// Answer `421 Misdirected Request` to a `Host` outside `allowed`, a no-op
+/// when the list is empty
+pub async fn allowed_hosts(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+    allowed: Arc<[String]>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let host = req
+        .headers()
+        .get(header::HOST)
+        .and_then(|val| val.to_str().ok())
+        .or_else(|| req.uri().authority().map(|authority| authority.as_str()))
+        .unwrap_or_default()
+        .to_owned();
+    if allowed.is_empty() || host_allowed(&host, &allowed) {
+        return next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    }
+
+    info!("Refused host {host:?}");
+    let res = HttpResponse::MisdirectedRequest().json(ErrorResponse::new("Misdirected Request"));
+    Ok(req.into_response(res).map_into_right_body())
+}
+
+/// `ALLOW_IPS` / `DENY_IPS`, a denied network wins over an allowed one
+#[derive(Debug, Default)]
+pub struct IpAcl {
+    pub allow: CidrList,
+    pub deny: CidrList,
+}
+
+impl IpAcl {
+    pub fn is_empty(&self) -> bool {
+        self.allow.0.is_empty() && self.deny.0.is_empty()
+    }
+
+    /// Whether `ip` may reach the service, an unknown address only passes
+    /// without an allow list
+    pub fn permits(&self, ip: Option<IpAddr>) -> bool {
+        let listed =
+            |list: &CidrList| ip.is_some_and(|ip| list.0.iter().any(|net| net.contains(ip)));
+        !listed(&self.deny) && (self.allow.0.is_empty() || listed(&self.allow))
+    }
+}
+
+/// Answer `403` to clients outside `acl`, the address is the one `client_ip`
+/// resolved so `TRUST_PROXY` applies. A no-op when both lists are empty
+pub async fn ip_acl(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+    acl: Arc<IpAcl>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let ip = req
+        .extensions()
+        .get::<ClientIp>()
+        .map_or_else(|| resolve_client_ip(&req, false), |client| client.0);
+    if acl.permits(ip) {
+        return next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    }
+
+    info!("Refused client {ip:?} by the IP access list");
+    let res = HttpResponse::Forbidden().json(ErrorResponse::new("Forbidden"));
+    Ok(req.into_response(res).map_into_right_body())
+}
+
+/// Long-lived streams, which would hold a `MAX_CONCURRENT` permit for their
+/// whole life
+fn is_stream(req: &ServiceRequest) -> bool {
+    let headers = req.headers();
+    headers
+        .get(header::ACCEPT)
+        .and_then(|val| val.to_str().ok())
+        .is_some_and(|val| val.contains("text/event-stream"))
+        || headers
+            .get(header::UPGRADE)
+            .and_then(|val| val.to_str().ok())
+            .is_some_and(|val| val.eq_ignore_ascii_case("websocket"))
+}
+
+/// Liveness probes, answered without shared state and never limited
+const PROBE_PATHS: &[&str] = &["/health", "/livez"];
+
+/// Answer `503` with `Retry-After` once `limit` has no permit left, a permit
+/// is held until the response is produced. A no-op without a limit, streams
+/// and probes skip it
+pub async fn concurrency_limit(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+    limit: Option<Arc<Semaphore>>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let Some(limit) = limit.filter(|_| !is_stream(&req) && !PROBE_PATHS.contains(&req.path()))
+    else {
+        return next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    };
+
+    match limit.try_acquire_owned() {
+        Ok(_permit) => next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body),
+        Err(_) => {
+            warn!("Too many concurrent requests, refused {}", req.path());
+            let res = HttpResponse::ServiceUnavailable()
+                .insert_header((header::RETRY_AFTER, "1"))
+                .json(ErrorResponse::new("Service Unavailable"));
+            Ok(req.into_response(res).map_into_right_body())
+        }
+    }
+}
+
+/// Length of the fixed window `RATE_LIMIT` / `RATE_LIMITS` count requests in
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+/// Windows tracked at most, the oldest are dropped past it so a flood of client
+/// addresses can't grow the map
+const RATE_LIMIT_MAX_WINDOWS: usize = 10_000;
+
+/// Index of the limit in `RateLimiter::limits`, `None` for the default one,
+/// and the client
+type WindowKey = (Option<usize>, Option<IpAddr>);
+
+#[derive(Debug, Default)]
+struct Windows {
+    /// Start and count of the current window of every limit and client
+    counts: HashMap<WindowKey, (Instant, u32)>,
+    /// The same windows by start, oldest first
+    starts: VecDeque<(Instant, WindowKey)>,
+}
+
+/// Requests per client and minute, a listed pattern has its own count and
+/// limit while the unlisted paths share the `default` one. A limit of 0 leaves
+/// its paths unlimited
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    default: u32,
+    limits: Vec<(ResourceDef, u32)>,
+    windows: Mutex<Windows>,
+}
+
+impl RateLimiter {
+    pub fn new(default: u32, limits: &RateLimitList) -> Self {
+        let mut limits: Vec<_> = limits.0.iter().collect();
+        // the most specific pattern wins whatever the listed order, the one
+        // with the most static segments
+        limits.sort_by_key(|(pattern, _)| {
+            std::cmp::Reverse(
+                pattern
+                    .split('/')
+                    .filter(|segment| !segment.is_empty() && !segment.contains('{'))
+                    .count(),
+            )
+        });
+        Self {
+            default,
+            limits: limits
+                .into_iter()
+                .map(|(pattern, limit)| (ResourceDef::new(pattern.as_str()), *limit))
+                .collect(),
+            windows: Mutex::default(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.default == 0 && self.limits.iter().all(|(_, limit)| *limit == 0)
+    }
+
+    /// Count a request to `path` from `ip` at `now`, `None` when it is within
+    /// its limit, the wait until its window resets otherwise
+    pub fn check(&self, path: &str, ip: Option<IpAddr>, now: Instant) -> Option<Duration> {
+        let (index, limit) = match self.limits.iter().position(|(def, _)| def.is_match(path)) {
+            Some(index) => (Some(index), self.limits[index].1),
+            None => (None, self.default),
+        };
+        if limit == 0 {
+            return None;
+        }
+
+        let mut windows = self.windows.lock().unwrap();
+        let Windows { counts, starts } = &mut *windows;
+        // drop the expired windows, then the oldest ones past the cap
+        while let Some(&(start, key)) = starts.front() {
+            if now.duration_since(start) < RATE_LIMIT_WINDOW
+                && counts.len() < RATE_LIMIT_MAX_WINDOWS
+            {
+                break;
+            }
+            starts.pop_front();
+            if counts
+                .get(&key)
+                .is_some_and(|(current, _)| *current == start)
+            {
+                counts.remove(&key);
+            }
+        }
+        let key = (index, ip);
+        let (start, count) = counts.entry(key).or_insert_with(|| {
+            starts.push_back((now, key));
+            (now, 0)
+        });
+        if now.duration_since(*start) >= RATE_LIMIT_WINDOW {
+            (*start, *count) = (now, 0);
+            starts.push_back((now, key));
+        }
+        if *count < limit {
+            *count += 1;
+            return None;
+        }
+        Some(RATE_LIMIT_WINDOW - now.duration_since(*start))
+    }
+}
+
+/// Answer `429` with `Retry-After` to clients over their `limiter` limit, the
+/// address is the one `client_ip` resolved. Probes are never limited
+pub async fn rate_limit(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+    limiter: Arc<RateLimiter>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let ip = req
+        .extensions()
+        .get::<ClientIp>()
+        .map_or_else(|| resolve_client_ip(&req, false), |client| client.0);
+    let wait = (!PROBE_PATHS.contains(&req.path()))
+        .then(|| limiter.check(req.path(), ip, Instant::now()))
+        .flatten();
+    let Some(wait) = wait else {
+        return next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    };
+
+    info!("Rate limited {path} for client {ip:?}", path = req.path());
+    let res = HttpResponse::TooManyRequests()
+        .insert_header((header::RETRY_AFTER, wait.as_secs_f64().ceil().to_string()))
+        .json(ErrorResponse::new("Too Many Requests"));
+    Ok(req.into_response(res).map_into_right_body())
+}
+
+/// Hide the wrapped scope on every listener but the admin ones, a no-op when
+/// no listener is marked admin
+pub async fn admin_listener_only(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+    admin: Arc<[SocketAddr]>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let local = req.app_config().local_addr();
+    if admin.is_empty() || admin.contains(&local) {
+        return next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    }
+
+    info!(
+        "{path} refused on non admin listener {local}",
+        path = req.path()
+    );
+    let res = HttpResponse::NotFound().json(ErrorResponse::new("Not Found"));
+    Ok(req.into_response(res).map_into_right_body())
+}
+
+/// Equality that takes the same time wherever the first difference is, so the
+/// token can't be guessed byte by byte from response times
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Require `Authorization: Bearer <token>` on the wrapped scope, `401`
+/// otherwise. Without a token the scope is disabled and answers `404`
+pub async fn admin_token(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+    token: Option<Arc<str>>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let Some(token) = token else {
+        info!("{path} refused, ADMIN_TOKEN is unset", path = req.path());
+        let res = HttpResponse::NotFound().json(ErrorResponse::new("Not Found"));
+        return Ok(req.into_response(res).map_into_right_body());
+    };
+
+    let bearer = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|val| val.to_str().ok())
+        .and_then(|val| val.strip_prefix("Bearer "));
+    if bearer.is_some_and(|bearer| constant_time_eq(bearer.trim().as_bytes(), token.as_bytes())) {
+        return next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    }
+
+    warn!(
+        "{path} refused, missing or wrong admin token",
+        path = req.path()
+    );
+    let res = HttpResponse::Unauthorized()
+        .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+        .json(ErrorResponse::new("Unauthorized"));
+    Ok(req.into_response(res).map_into_right_body())
+}
+
+#[actix_web::test]
+async fn test_compression_threshold() {
+    use actix_web::{
+        middleware::{from_fn, Compress},
+        test, web, App,
+    };
+
+    let app = test::init_service(
+        App::new()
+            .wrap(from_fn(move |req, next| {
+                compression_threshold(req, next, 1024)
+            }))
+            .wrap(Compress::default())
+            .wrap(from_fn(strip_identity_encoding))
+            .route("/small", web::get().to(|| async { "x".repeat(100) }))
+            .route("/large", web::get().to(|| async { "x".repeat(4096) })),
+    )
+    .await;
+
+    for (uri, encoding) in [("/small", None), ("/large", Some("gzip"))] {
+        let req = test::TestRequest::get()
+            .uri(uri)
+            .insert_header((header::ACCEPT_ENCODING, "gzip"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(
+            res.headers()
+                .get(header::CONTENT_ENCODING)
+                .and_then(|val| val.to_str().ok()),
+            encoding
+        );
+    }
+}
+
+#[actix_web::test]
+async fn test_allowed_hosts() {
+    use actix_web::{middleware::from_fn, test, web, App};
+
+    let allowed: Arc<[String]> =
+        vec!["time.example.com".to_owned(), "127.0.0.1:3000".to_owned()].into();
+    let app = test::init_service(
+        App::new()
+            .wrap(from_fn(move |req, next| {
+                allowed_hosts(req, next, allowed.clone())
+            }))
+            .route("/now", web::get().to(HttpResponse::Ok)),
+    )
+    .await;
+
+    for (host, status) in [
+        ("time.example.com", 200),
+        ("TIME.example.com:8443", 200),
+        ("127.0.0.1:3000", 200),
+        ("127.0.0.1:4000", 421),
+        ("evil.example.com", 421),
+    ] {
+        let req = test::TestRequest::get()
+            .uri("/now")
+            .insert_header((header::HOST, host))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status().as_u16(), status, "{host}");
+    }
+}
+
+#[actix_web::test]
+async fn test_ip_acl() {
+    use actix_web::{middleware::from_fn, test, web, App};
+
+    let acl = Arc::new(IpAcl {
+        allow: "10.0.0.0/8,192.168.1.0/24".parse().unwrap(),
+        deny: "10.0.0.66".parse().unwrap(),
+    });
+    let app = test::init_service(
+        App::new()
+            .wrap(from_fn(move |req, next| ip_acl(req, next, acl.clone())))
+            .wrap(from_fn(|req, next| client_ip(req, next, true, None)))
+            .route("/now", web::get().to(HttpResponse::Ok)),
+    )
+    .await;
+
+    for (forwarded, status) in [
+        ("10.1.2.3", 200),
+        ("192.168.1.200", 200),
+        ("10.0.0.66", 403),
+        ("192.168.2.1", 403),
+        ("8.8.8.8, 10.1.2.3", 403),
+    ] {
+        // behind the trusted proxy 172.16.0.1, the forwarded address is the client
+        let req = test::TestRequest::get()
+            .uri("/now")
+            .peer_addr("172.16.0.1:4000".parse().unwrap())
+            .insert_header((X_FORWARDED_FOR, forwarded))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status().as_u16(), status, "{forwarded}");
+    }
+
+    assert!(IpAcl::default().permits(None));
+    let deny_only = IpAcl {
+        allow: CidrList::default(),
+        deny: "10.0.0.0/8".parse().unwrap(),
+    };
+    assert!(deny_only.permits(Some("8.8.8.8".parse().unwrap())));
+    assert!(!deny_only.permits(Some("10.9.9.9".parse().unwrap())));
+    assert!(deny_only.permits(None));
+}
+
+#[actix_web::test]
+async fn test_concurrency_limit() {
+    use actix_web::{middleware::from_fn, test, web, App};
+
+    let limit = Arc::new(Semaphore::new(1));
+    let app = test::init_service(
+        App::new()
+            .wrap(from_fn({
+                let limit = limit.clone();
+                move |req, next| concurrency_limit(req, next, Some(limit.clone()))
+            }))
+            .route("/now", web::get().to(HttpResponse::Ok))
+            .route("/health", web::get().to(HttpResponse::Ok)),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/now").to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+
+    // an in-flight request holds the only permit
+    let in_flight = limit.try_acquire().unwrap();
+    for _ in 0..3 {
+        let req = test::TestRequest::get().uri("/now").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status().as_u16(), 503);
+        assert_eq!(res.headers().get(header::RETRY_AFTER).unwrap(), "1");
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/now")
+        .insert_header((header::ACCEPT, "text/event-stream"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+    let req = test::TestRequest::get().uri("/health").to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+
+    drop(in_flight);
+    let req = test::TestRequest::get().uri("/now").to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+}
+
+#[actix_web::test]
+async fn test_rate_limit() {
+    use actix_web::{middleware::from_fn, test, web, App};
+
+    let limiter = Arc::new(RateLimiter::new(
+        2,
+        &"/now=3,/offsets=1,/now/{continent}/{region}=0"
+            .parse()
+            .unwrap(),
+    ));
+    let app = test::init_service(
+        App::new()
+            .wrap(from_fn({
+                let limiter = limiter.clone();
+                move |req, next| rate_limit(req, next, limiter.clone())
+            }))
+            .route("/now", web::get().to(HttpResponse::Ok))
+            .route("/now/unix", web::get().to(HttpResponse::Ok))
+            .route("/now/{continent}/{region}", web::get().to(HttpResponse::Ok))
+            .route("/offsets", web::post().to(HttpResponse::Ok))
+            .route("/health", web::get().to(HttpResponse::Ok)),
+    )
+    .await;
+    let client: SocketAddr = "10.0.0.1:4000".parse().unwrap();
+    let status = |req: test::TestRequest| {
+        let app = &app;
+        async move {
+            test::call_service(app, req.peer_addr(client).to_request())
+                .await
+                .status()
+                .as_u16()
+        }
+    };
+
+    // the batch endpoint runs out first, /now keeps its own allowance
+    assert_eq!(status(test::TestRequest::post().uri("/offsets")).await, 200);
+    let req = test::TestRequest::post().uri("/offsets").peer_addr(client);
+    let res = test::call_service(&app, req.to_request()).await;
+    assert_eq!(res.status().as_u16(), 429);
+    assert_eq!(res.headers().get(header::RETRY_AFTER).unwrap(), "60");
+    for expected in [200, 200, 200, 429] {
+        assert_eq!(status(test::TestRequest::get().uri("/now")).await, expected);
+    }
+    assert_eq!(status(test::TestRequest::post().uri("/offsets")).await, 429);
+
+    // unlisted paths share the default, a 0 limit and probes are unlimited
+    for expected in [200, 200, 429] {
+        let req = test::TestRequest::get().uri("/now/unix");
+        assert_eq!(status(req).await, expected);
+    }
+    for _ in 0..5 {
+        let req = test::TestRequest::get().uri("/now/europe/berlin");
+        assert_eq!(status(req).await, 200);
+        assert_eq!(status(test::TestRequest::get().uri("/health")).await, 200);
+    }
+
+    // other clients are counted apart
+    let req = test::TestRequest::post()
+        .uri("/offsets")
+        .peer_addr("10.0.0.2:4000".parse().unwrap());
+    assert_eq!(
+        test::call_service(&app, req.to_request()).await.status(),
+        200
+    );
+
+    let ip = Some(client.ip());
+    let later = Instant::now() + RATE_LIMIT_WINDOW;
+    assert_eq!(limiter.check("/offsets", ip, later), None);
+    assert!(limiter.check("/offsets", ip, later).is_some());
+
+    // overlapping patterns resolve to the most specific in either order
+    for limits in [
+        "/now/{continent}/{region}=1,/now/offset/{offset}=3",
+        "/now/offset/{offset}=3,/now/{continent}/{region}=1",
+    ] {
+        let limiter = RateLimiter::new(0, &limits.parse().unwrap());
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(limiter.check("/now/offset/+02:00", ip, now), None);
+        }
+        assert!(limiter.check("/now/offset/+02:00", ip, now).is_some());
+        assert_eq!(limiter.check("/now/europe/berlin", ip, now), None);
+        assert!(limiter.check("/now/europe/berlin", ip, now).is_some());
+    }
+
+    // a flood of clients drops the oldest windows
+    let start = Instant::now();
+    for n in 0..=RATE_LIMIT_MAX_WINDOWS as u32 {
+        limiter.check("/offsets", Some(IpAddr::from(n.to_be_bytes())), start);
+    }
+    let windows = limiter.windows.lock().unwrap();
+    assert_eq!(windows.counts.len(), RATE_LIMIT_MAX_WINDOWS);
+    assert_eq!(windows.starts.len(), RATE_LIMIT_MAX_WINDOWS);
+    assert!(!windows
+        .counts
+        .contains_key(&(Some(1), Some(IpAddr::from([0; 4])))));
+    drop(windows);
+
+    assert!(RateLimiter::default().is_empty());
+    assert_eq!(RateLimiter::default().check("/now", ip, later), None);
+}
+
+#[actix_web::test]
+async fn test_admin_listener_only() {
+    use actix_web::{middleware::from_fn, test, web, App};
+
+    // test requests are served on 127.0.0.1:8080
+    for (admin, status) in [
+        (vec![], 200),
+        (vec!["127.0.0.1:8080".parse().unwrap()], 200),
+        (vec!["127.0.0.1:9000".parse().unwrap()], 404),
+    ] {
+        let admin: Arc<[SocketAddr]> = admin.into();
+        let app = test::init_service(
+            App::new().service(
+                web::scope("/admin")
+                    .wrap(from_fn(move |req, next| {
+                        admin_listener_only(req, next, admin.clone())
+                    }))
+                    .route("/ping", web::get().to(HttpResponse::Ok)),
+            ),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/admin/ping").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status().as_u16(), status);
+    }
+}
+
+#[actix_web::test]
+async fn test_admin_token() {
+    use actix_web::{middleware::from_fn, test, web, App};
+
+    for (token, authorization, status) in [
+        (Some("s3cret"), Some("Bearer s3cret"), 200),
+        (Some("s3cret"), Some("Bearer s3cres"), 401),
+        (Some("s3cret"), Some("Bearer s3cret2"), 401),
+        (Some("s3cret"), Some("Basic s3cret"), 401),
+        (Some("s3cret"), None, 401),
+        // unset disables the scope whatever the request carries
+        (None, Some("Bearer s3cret"), 404),
+        (None, None, 404),
+    ] {
+        let token: Option<Arc<str>> = token.map(Into::into);
+        let app = test::init_service(
+            App::new().service(
+                web::scope("/admin")
+                    .wrap(from_fn(move |req, next| {
+                        admin_token(req, next, token.clone())
+                    }))
+                    .route("/ping", web::get().to(HttpResponse::Ok)),
+            ),
+        )
+        .await;
+
+        let mut req = test::TestRequest::get().uri("/admin/ping");
+        if let Some(authorization) = authorization {
+            req = req.insert_header((header::AUTHORIZATION, authorization));
+        }
+        let res = test::call_service(&app, req.to_request()).await;
+        assert_eq!(res.status().as_u16(), status, "{authorization:?}");
+        if status == 401 {
+            assert_eq!(
+                res.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+                "Bearer"
+            );
+        }
+    }
+
+    assert!(constant_time_eq(b"abc", b"abc"));
+    assert!(!constant_time_eq(b"abc", b"abd"));
+    assert!(!constant_time_eq(b"abc", b"ab"));
+}
+
+#[test]
+fn test_access_log_level() {
+    let threshold = Some(Duration::from_millis(500));
+    assert_eq!(
+        access_log_level(Duration::from_millis(3), threshold),
+        Level::Debug
+    );
+    assert_eq!(
+        access_log_level(Duration::from_millis(800), threshold),
+        Level::Warn
+    );
+    assert_eq!(
+        access_log_level(Duration::from_millis(3), None),
+        Level::Info
+    );
+}
+
+#[test]
+fn test_resolve_client_ip_trusted_proxy() {
+    let req = actix_web::test::TestRequest::default()
+        .peer_addr("10.0.0.1:4000".parse().unwrap())
+        .insert_header((X_FORWARDED_FOR, "203.0.113.7, 10.0.0.2"))
+        .to_srv_request();
+
+    assert_eq!(
+        resolve_client_ip(&req, true),
+        Some("203.0.113.7".parse().unwrap())
+    );
+}
+
+#[test]
+fn test_resolve_client_ip_untrusted_proxy() {
+    let req = actix_web::test::TestRequest::default()
+        .peer_addr("10.0.0.1:4000".parse().unwrap())
+        .insert_header((X_FORWARDED_FOR, "203.0.113.7"))
+        .to_srv_request();
+
+    assert_eq!(
+        resolve_client_ip(&req, false),
+        Some("10.0.0.1".parse().unwrap())
+    );
+}
+
+#[test]
+fn test_resolve_client_ip_invalid_header() {
+    let req = actix_web::test::TestRequest::default()
+        .peer_addr("10.0.0.1:4000".parse().unwrap())
+        .insert_header((X_FORWARDED_FOR, "not-an-ip"))
+        .to_srv_request();
+
+    assert_eq!(
+        resolve_client_ip(&req, true),
+        Some("10.0.0.1".parse().unwrap())
+    );
+}
@@ -0,0 +1,158 @@
+//! In-process SNTP responder so tests don't depend on a public NTP server
+
+use std::{
+    net::{SocketAddr, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+
+use crate::{app_context::NTP_UNIX_OFFSET, leap::LeapIndicator};
+
+const PACKET_SIZE: usize = 48;
+
+struct State {
+    time: Mutex<DateTime<Utc>>,
+    leap: Mutex<LeapIndicator>,
+    stratum: AtomicU8,
+    mode: AtomicU8,
+    echo_origin: AtomicBool,
+    requests: AtomicUsize,
+    stop: AtomicBool,
+}
+
+pub struct MockNtpServer {
+    addr: SocketAddr,
+    state: Arc<State>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MockNtpServer {
+    /// Bind an ephemeral UDP port and answer every request with `time`
+    pub fn start(time: DateTime<Utc>) -> Self {
+        Self::start_on("127.0.0.1:0", time)
+    }
+
+    /// Same as `start` on the given local address, such as `[::1]:0`
+    pub fn start_on(bind: &str, time: DateTime<Utc>) -> Self {
+        let socket = UdpSocket::bind(bind).expect("bind mock ntp socket");
+        socket
+            .set_read_timeout(Some(Duration::from_millis(50)))
+            .expect("set mock ntp timeout");
+        let addr = socket.local_addr().expect("mock ntp address");
+
+        let state = Arc::new(State {
+            time: Mutex::new(time),
+            leap: Mutex::new(LeapIndicator::None),
+            stratum: AtomicU8::new(2),
+            mode: AtomicU8::new(4),
+            echo_origin: AtomicBool::new(true),
+            requests: AtomicUsize::new(0),
+            stop: AtomicBool::new(false),
+        });
+
+        let thread_state = state.clone();
+        let handle = thread::spawn(move || {
+            let mut buf = [0u8; PACKET_SIZE];
+            while !thread_state.stop.load(Ordering::Relaxed) {
+                let Ok((_, peer)) = socket.recv_from(&mut buf) else {
+                    continue;
+                };
+                thread_state.requests.fetch_add(1, Ordering::Relaxed);
+                let time = *thread_state.time.lock().unwrap();
+                let leap = *thread_state.leap.lock().unwrap();
+                let stratum = thread_state.stratum.load(Ordering::Relaxed);
+                let mut packet = response(&buf, time, leap, stratum);
+                packet[0] = (packet[0] & !7) | thread_state.mode.load(Ordering::Relaxed);
+                if !thread_state.echo_origin.load(Ordering::Relaxed) {
+                    packet[24..32].fill(0);
+                }
+                let _ = socket.send_to(&packet, peer);
+            }
+        });
+
+        Self {
+            addr,
+            state,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn address(&self) -> String {
+        self.addr.to_string()
+    }
+
+    pub fn set_time(&self, time: DateTime<Utc>) {
+        *self.state.time.lock().unwrap() = time;
+    }
+
+    pub fn set_leap(&self, leap: LeapIndicator) {
+        *self.state.leap.lock().unwrap() = leap;
+    }
+
+    /// Stratum of the following responses, 2 by default
+    pub fn set_stratum(&self, stratum: u8) {
+        self.state.stratum.store(stratum, Ordering::Relaxed);
+    }
+
+    /// Mode of the following responses, 4 (server) by default
+    pub fn set_mode(&self, mode: u8) {
+        self.state.mode.store(mode, Ordering::Relaxed);
+    }
+
+    /// Zero the origin timestamp of the following responses instead of echoing
+    /// the request, as a stale or forged reply would
+    pub fn set_echo_origin(&self, echo: bool) {
+        self.state.echo_origin.store(echo, Ordering::Relaxed);
+    }
+
+    pub fn requests(&self) -> usize {
+        self.state.requests.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for MockNtpServer {
+    fn drop(&mut self) {
+        self.state.stop.store(true, Ordering::Relaxed);
+        // wait for the socket to be closed so the port stops answering
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn timestamp(time: DateTime<Utc>) -> u64 {
+    let sec = (time.timestamp() + NTP_UNIX_OFFSET) as u64;
+    let frac = (u64::from(time.timestamp_subsec_nanos()) << 32) / 1_000_000_000;
+    (sec << 32) | frac
+}
+
+/// Server packet: version 4, server mode
+fn response(
+    request: &[u8; PACKET_SIZE],
+    time: DateTime<Utc>,
+    leap: LeapIndicator,
+    stratum: u8,
+) -> [u8; PACKET_SIZE] {
+    let li = match leap {
+        LeapIndicator::None => 0,
+        LeapIndicator::Insert => 1,
+        LeapIndicator::Delete => 2,
+        LeapIndicator::Alarm => 3,
+    };
+    let mut packet = [0u8; PACKET_SIZE];
+    packet[0] = (li << 6) | (4 << 3) | 4;
+    packet[1] = stratum;
+    packet[12..16].copy_from_slice(&[127, 0, 0, 1]);
+    packet[16..24].copy_from_slice(&timestamp(time).to_be_bytes());
+    // origin timestamp echoes the client's transmit timestamp
+    packet[24..32].copy_from_slice(&request[40..48]);
+    packet[32..40].copy_from_slice(&timestamp(time).to_be_bytes());
+    packet[40..48].copy_from_slice(&timestamp(time).to_be_bytes());
+    packet
+}
@@ -0,0 +1,59 @@
+//! In-process HTTP responder standing in for a peer node's `/now` or an NTP discovery source
+
+use std::{
+    io::{Read, Write},
+    net::{SocketAddr, TcpListener},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+pub struct MockPeer {
+    addr: SocketAddr,
+    requests: Arc<AtomicUsize>,
+}
+
+impl MockPeer {
+    /// Answer every request with `body` as JSON until the test process exits
+    pub fn start(body: serde_json::Value) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind mock peer socket");
+        let addr = listener.local_addr().expect("mock peer address");
+        let requests = Arc::new(AtomicUsize::new(0));
+
+        let counter = requests.clone();
+        thread::spawn(move || {
+            let body = body.to_string();
+            for mut stream in listener.incoming().flatten() {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {len}\r\nConnection: close\r\n\r\n{body}",
+                    len = body.len()
+                );
+            }
+        });
+
+        Self { addr, requests }
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Requests answered so far
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+}
+
+/// Envelope body of a peer `/now` at `time`
+pub fn now_body(time: chrono::DateTime<chrono::Utc>, source: &str) -> serde_json::Value {
+    serde_json::json!({
+        "data": crate::models::Time::from(time),
+        "meta": { "source": source },
+    })
+}
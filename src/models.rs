@@ -1,7 +1,142 @@
-use chrono::{DateTime, Datelike, Timelike};
+use std::{collections::HashMap, str::FromStr, sync::OnceLock};
+
+use chrono::{DateTime, Datelike, DurationRound, NaiveDate, Offset, TimeDelta, Timelike};
+use serde::{ser::SerializeMap, Serializer};
 use serde_derive::{Deserialize, Serialize};
 
-#[derive(Serialize)]
+use crate::{
+    app_context::{Confidence, TimeSample, TimeSource},
+    astro::Astro,
+    leap::LeapIndicator,
+    retry_budget::RetryBudgetStatus,
+    time_scale::TimeScale,
+};
+
+/// Julian Day of the Unix epoch (1970-01-01T00:00:00Z)
+const UNIX_EPOCH_JULIAN_DAY: f64 = 2_440_587.5;
+/// Offset between the Julian Day and the Modified Julian Date
+const MJD_OFFSET: f64 = 2_400_000.5;
+const SECONDS_PER_DAY: f64 = 86_400.0;
+/// Longest `{continent}` or `{region}` accepted as well formed, well above
+/// the longest IANA component
+const MAX_ZONE_SEGMENT: usize = 64;
+
+/// Serialized names of the `Time` fields, also the stable order of their keys:
+/// optional fields keep their slot and are left out rather than moved, so the
+/// keys of a `Time` are always a subsequence of this list
+const TIME_FIELDS: [&str; 11] = [
+    "year",
+    "month",
+    "day",
+    "hour",
+    "minutes",
+    "seconds",
+    "millis",
+    "nanos",
+    "julian_day",
+    "mjd",
+    "tz",
+];
+
+/// Keys `Time` serializes after `TIME_FIELDS`, no field may be renamed to them
+const RESERVED_FIELDS: [&str; 2] = ["calendar", "astro"];
+
+static FIELD_NAMES: OnceLock<FieldNames> = OnceLock::new();
+static DEFAULT_PRECISION: OnceLock<Precision> = OnceLock::new();
+
+/// Sub-second fields of a `Time`, `millis` adds `millis` and `nanos` adds
+/// both `millis` and `nanos` (the full sub-second part in nanoseconds)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Precision {
+    #[default]
+    Seconds,
+    Millis,
+    Nanos,
+}
+
+impl FromStr for Precision {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "seconds" => Ok(Self::Seconds),
+            "millis" => Ok(Self::Millis),
+            "nanos" => Ok(Self::Nanos),
+            _ => Err(format!("Unknown precision {s}")),
+        }
+    }
+}
+
+impl Precision {
+    /// Precision of every `Time` not given one with `with_precision`, only the
+    /// first call has an effect
+    pub fn install_default(self) {
+        let _ = DEFAULT_PRECISION.set(self);
+    }
+}
+
+/// Renames of the `Time` fields for downstream schemas, parsed from
+/// `minutes:min,seconds:sec`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FieldNames(HashMap<&'static str, String>);
+
+impl FromStr for FieldNames {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut names = HashMap::new();
+        for pair in s.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (field, name) = pair
+                .split_once(':')
+                .ok_or_else(|| format!("Expected field:name, got {pair}"))?;
+            let field = TIME_FIELDS
+                .iter()
+                .find(|known| **known == field.trim())
+                .ok_or_else(|| format!("Unknown field {field}"))?;
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(format!("Empty name for field {field}"));
+            }
+            if RESERVED_FIELDS.contains(&name) {
+                return Err(format!("Field name {name} is reserved"));
+            }
+            if names.insert(*field, name.to_owned()).is_some() {
+                return Err(format!("Field {field} renamed twice"));
+            }
+        }
+
+        let mut taken: Vec<_> = TIME_FIELDS
+            .iter()
+            .map(|field| names.get(field).map_or(*field, String::as_str))
+            .collect();
+        taken.sort_unstable();
+        if taken.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err("Renamed fields must stay unique".to_owned());
+        }
+        Ok(Self(names))
+    }
+}
+
+impl serde::Serialize for FieldNames {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl FieldNames {
+    /// Apply the renames to every `Time` serialized from now on, only the first
+    /// call has an effect
+    pub fn install(self) {
+        let _ = FIELD_NAMES.set(self);
+    }
+
+    fn name(&self, field: &'static str) -> &str {
+        self.0.get(field).map_or(field, String::as_str)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Time {
     year: u16,
     month: u8,
@@ -9,6 +144,104 @@ pub struct Time {
     hour: u8,
     minutes: u8,
     seconds: u8,
+    subsec_nanos: u32,
+    /// `DEFAULT_PRECISION` unless set
+    precision: Option<Precision>,
+    julian_day: f64,
+    mjd: f64,
+    /// Zone the fields are expressed in, the fixed offset unless named with `with_tz`
+    tz: String,
+    /// Same date in another calendar, serialized after the other fields
+    calendar: Option<CalendarDate>,
+    /// Moon phase of the instant, serialized after the calendar
+    astro: Option<Astro>,
+}
+
+impl Time {
+    /// Serialize the fields in `TIME_FIELDS` order under their installed names
+    fn serialize_with<S: Serializer>(
+        &self,
+        names: &FieldNames,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let precision = self
+            .precision
+            .or_else(|| DEFAULT_PRECISION.get().copied())
+            .unwrap_or_default();
+
+        let mut map = serializer.serialize_map(None)?;
+        for field in TIME_FIELDS {
+            let name = names.name(field);
+            match field {
+                "year" => map.serialize_entry(name, &self.year)?,
+                "month" => map.serialize_entry(name, &self.month)?,
+                "day" => map.serialize_entry(name, &self.day)?,
+                "hour" => map.serialize_entry(name, &self.hour)?,
+                "minutes" => map.serialize_entry(name, &self.minutes)?,
+                "seconds" => map.serialize_entry(name, &self.seconds)?,
+                "millis" if precision != Precision::Seconds => {
+                    map.serialize_entry(name, &(self.subsec_nanos / 1_000_000))?
+                }
+                "nanos" if precision == Precision::Nanos => {
+                    map.serialize_entry(name, &self.subsec_nanos)?
+                }
+                "julian_day" => map.serialize_entry(name, &self.julian_day)?,
+                "mjd" => map.serialize_entry(name, &self.mjd)?,
+                "tz" => map.serialize_entry(name, &self.tz)?,
+                _ => {}
+            }
+        }
+        if let Some(calendar) = &self.calendar {
+            map.serialize_entry("calendar", calendar)?;
+        }
+        if let Some(astro) = &self.astro {
+            map.serialize_entry("astro", astro)?;
+        }
+        map.end()
+    }
+}
+
+impl Time {
+    pub fn with_tz(mut self, tz: impl Into<String>) -> Self {
+        self.tz = tz.into();
+        self
+    }
+
+    /// Override `DEFAULT_PRECISION`, `None` keeps it
+    pub fn with_precision(mut self, precision: Option<Precision>) -> Self {
+        self.precision = precision.or(self.precision);
+        self
+    }
+
+    /// Add the date in `calendar` under `calendar`, nothing for Gregorian
+    pub fn with_calendar(mut self, calendar: Calendar) -> Self {
+        self.calendar = NaiveDate::from_ymd_opt(
+            i32::from(self.year),
+            u32::from(self.month),
+            u32::from(self.day),
+        )
+        .and_then(|date| calendar.date(date));
+        self
+    }
+
+    /// Add `astro` under `astro`, computed by the caller from the UTC instant
+    pub fn with_astro(mut self, astro: Option<Astro>) -> Self {
+        self.astro = astro;
+        self
+    }
+
+    /// Restore the sub-second part of a time converted from a whole second
+    pub fn with_subsec_nanos(mut self, nanos: u32) -> Self {
+        self.subsec_nanos = nanos;
+        self
+    }
+}
+
+impl serde::Serialize for Time {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let default = FieldNames::default();
+        self.serialize_with(FIELD_NAMES.get().unwrap_or(&default), serializer)
+    }
 }
 
 impl<T> From<DateTime<T>> for Time
@@ -16,6 +249,10 @@ where
     T: chrono::TimeZone,
 {
     fn from(value: DateTime<T>) -> Self {
+        let unix_seconds =
+            value.timestamp() as f64 + f64::from(value.timestamp_subsec_nanos()) / 1e9;
+        let julian_day = unix_seconds / SECONDS_PER_DAY + UNIX_EPOCH_JULIAN_DAY;
+
         Self {
             year: value.year() as u16,
             month: value.month() as u8,
@@ -23,12 +260,936 @@ where
             hour: value.hour() as u8,
             minutes: value.minute() as u8,
             seconds: value.second() as u8,
+            subsec_nanos: value.timestamp_subsec_nanos(),
+            precision: None,
+            julian_day,
+            mjd: julian_day - MJD_OFFSET,
+            tz: value.offset().fix().to_string(),
+            calendar: None,
+            astro: None,
+        }
+    }
+}
+
+/// How sub-second time maps to the whole `seconds` of a `Time`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rounding {
+    #[default]
+    Truncate,
+    /// Half a second or more rounds up, rolling over minutes, hours and days
+    Nearest,
+}
+
+impl FromStr for Rounding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "truncate" => Ok(Self::Truncate),
+            "nearest" => Ok(Self::Nearest),
+            _ => Err(format!("Unknown rounding {s}")),
+        }
+    }
+}
+
+impl Rounding {
+    pub fn apply<T: chrono::TimeZone>(self, time: DateTime<T>) -> DateTime<T> {
+        match self {
+            Self::Truncate => time,
+            Self::Nearest => time
+                .clone()
+                .duration_round(TimeDelta::seconds(1))
+                .unwrap_or(time),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ErrorResponse {
+    error: String,
+}
+
+impl ErrorResponse {
+    pub fn new(error: impl Into<String>) -> Self {
+        Self {
+            error: error.into(),
+        }
+    }
+}
+
+/// Error for an unknown name, with close known names the client may have meant
+#[derive(Serialize)]
+pub struct SuggestionsResponse {
+    pub error: String,
+    pub suggestions: Vec<&'static str>,
+}
+
+#[derive(Serialize)]
+pub struct Ready {
+    pub ready: bool,
+}
+
+#[derive(Serialize)]
+pub struct CacheFlush {
+    pub flushed: bool,
+    pub previous_cache_age_s: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NtpStatus {
+    pub server: String,
+    pub synced: bool,
+    pub last_sync: Option<String>,
+    pub cache_age_s: Option<u64>,
+    pub leap: LeapIndicator,
+    /// When the announced leap second happens
+    pub leap_at: Option<String>,
+    /// `None` without `NTP_RETRY_BUDGET`
+    pub retry_budget: Option<RetryBudgetStatus>,
+    /// Transmit timestamp of the last sync as on the wire, seconds since 1900
+    /// in the high 32 bits and the fraction in the low ones. Diagnostic only
+    pub transmit_timestamp: Option<u64>,
+    /// The same decoded with its fraction
+    pub transmit_time: Option<String>,
+}
+
+/// Offset of the server clock from ours and round-trip delay of one SNTP
+/// exchange
+#[derive(Clone, Debug, Serialize)]
+pub struct NtpSample {
+    pub offset_ms: f64,
+    pub delay_ms: f64,
+    pub server: String,
+    /// Served again from the last exchange, a new one wasn't allowed yet
+    pub cached: bool,
+}
+
+/// Result of a diagnostic exchange with an arbitrary server, `error` is set
+/// when it failed
+#[derive(Debug, Serialize)]
+pub struct NtpTest {
+    pub server: String,
+    pub time: Option<String>,
+    pub offset_ms: Option<f64>,
+    pub delay_ms: Option<f64>,
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct NtpTestQuery {
+    pub server: String,
+}
+
+/// Body of the time endpoints, `http` is a plain text IMF-fixdate
+/// (`Sun, 06 Nov 1994 08:49:37 GMT`) always in GMT whatever the zone,
+/// `words` the local time in English (`quarter past three in the afternoon`)
+/// and `ordinal` the local ISO ordinal date (`2024-003`), `text` the local
+/// date and time (`2024-01-03T15:04:05`) shaped by `?sep=` and `?hour12=`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Json,
+    Http,
+    Words,
+    Ordinal,
+    Text,
+}
+
+/// Date / time separator of `?format=text`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+pub enum DateTimeSeparator {
+    #[default]
+    #[serde(rename = "T", alias = "t")]
+    T,
+    #[serde(rename = "space", alias = " ")]
+    Space,
+}
+
+impl DateTimeSeparator {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::T => "T",
+            Self::Space => " ",
+        }
+    }
+}
+
+/// `?calendar=` of `/now`, the date fields stay Gregorian and the other
+/// calendars are added as a `calendar` object
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Calendar {
+    #[default]
+    Gregorian,
+    /// ISO 8601 week date, the week year can differ from the calendar year
+    IsoWeek,
+    /// Proleptic Julian calendar, 13 days behind Gregorian from March 1900 to
+    /// February 2100
+    Julian,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "system", rename_all = "kebab-case")]
+pub enum CalendarDate {
+    IsoWeek {
+        year: i32,
+        week: u32,
+        /// 1 for Monday to 7 for Sunday
+        weekday: u32,
+    },
+    Julian {
+        year: i64,
+        month: u32,
+        day: u32,
+    },
+}
+
+impl Calendar {
+    /// `date` in this calendar, `None` for Gregorian
+    pub fn date(self, date: NaiveDate) -> Option<CalendarDate> {
+        match self {
+            Self::Gregorian => None,
+            Self::IsoWeek => {
+                let week = date.iso_week();
+                Some(CalendarDate::IsoWeek {
+                    year: week.year(),
+                    week: week.week(),
+                    weekday: date.weekday().number_from_monday(),
+                })
+            }
+            Self::Julian => {
+                // Julian Day Number to Julian calendar, Richards' algorithm
+                let jdn = i64::from(date.num_days_from_ce()) + 1_721_425;
+                let c = jdn + 32_082;
+                let d = (4 * c + 3).div_euclid(1461);
+                let e = c - (1461 * d).div_euclid(4);
+                let m = (5 * e + 2) / 153;
+                Some(CalendarDate::Julian {
+                    year: d - 4800 + m / 10,
+                    month: (m + 3 - 12 * (m / 10)) as u32,
+                    day: (e - (153 * m + 2) / 5 + 1) as u32,
+                })
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NowQuery {
+    #[serde(default)]
+    pub pretty: bool,
+    pub tz: Option<String>,
+    #[serde(default)]
+    pub envelope: bool,
+    /// Alias of `envelope`
+    #[serde(default)]
+    pub meta: bool,
+    #[serde(default)]
+    pub scale: TimeScale,
+    /// Overrides `DEFAULT_PRECISION`
+    pub precision: Option<Precision>,
+    #[serde(default)]
+    pub format: OutputFormat,
+    #[serde(default)]
+    pub calendar: Calendar,
+    /// Add the moon phase as an `astro` object
+    #[serde(default)]
+    pub astro: bool,
+    /// Modifiers of `format=text`
+    pub sep: Option<DateTimeSeparator>,
+    pub hour12: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct Meta {
+    source: TimeSource,
+    cache_age_s: Option<u64>,
+    leap: LeapIndicator,
+    /// Unix time of the NTP sync the time is interpolated from
+    synced_at: Option<i64>,
+    confidence: Confidence,
+    /// Stratum of the NTP server synced with, absent for other sources
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stratum: Option<u8>,
+    version: &'static str,
+    #[serde(flatten)]
+    remaining: Option<Remaining>,
+}
+
+impl Meta {
+    pub fn with_remaining(mut self, remaining: Remaining) -> Self {
+        self.remaining = Some(remaining);
+        self
+    }
+}
+
+/// Seconds left in the current minute, hour and day of the response zone
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Remaining {
+    pub seconds_to_next_minute: i64,
+    pub seconds_to_next_hour: i64,
+    pub seconds_to_next_day: i64,
+}
+
+impl From<&TimeSample> for Meta {
+    fn from(value: &TimeSample) -> Self {
+        Self {
+            source: value.source,
+            cache_age_s: value.cache_age,
+            leap: value.leap,
+            synced_at: value.synced_at,
+            confidence: value.confidence,
+            stratum: value.stratum,
+            version: env!("CARGO_PKG_VERSION"),
+            remaining: None,
         }
     }
 }
 
+#[derive(Serialize)]
+pub struct Envelope<T> {
+    pub data: T,
+    pub meta: Meta,
+}
+
+#[derive(Serialize)]
+pub struct ZonedTime {
+    pub utc: Time,
+    pub local: Time,
+    pub zone: String,
+    pub offset: String,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<ZoneAlias>,
+}
+
+/// A requested zone name IANA kept only for backward compatibility, with the
+/// name clients should migrate to
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ZoneAlias {
+    pub deprecated_alias: String,
+    pub canonical: String,
+}
+
 #[derive(Deserialize)]
 pub struct TimeZone {
     pub continent: String,
     pub region: String,
 }
+
+impl TimeZone {
+    /// Whether both segments could be a zone name component: not empty, at
+    /// most `MAX_ZONE_SEGMENT` long and made of the characters IANA uses
+    pub fn is_well_formed(&self) -> bool {
+        [&self.continent, &self.region].iter().all(|segment| {
+            (1..=MAX_ZONE_SEGMENT).contains(&segment.len())
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+'))
+        })
+    }
+
+    pub fn to_tz(&self) -> Result<chrono_tz::Tz, chrono_tz::ParseError> {
+        format!(
+            "{cont}/{region}",
+            cont = crate::utils::to_camel_case(&self.continent),
+            region = crate::utils::to_camel_case(&self.region)
+        )
+        .parse()
+    }
+}
+
+#[derive(Serialize)]
+pub struct ZoneOffset {
+    pub zone: String,
+    pub at: String,
+    pub offset: String,
+    pub offset_seconds: i32,
+    pub abbreviation: String,
+    pub is_dst: bool,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<ZoneAlias>,
+}
+
+/// Item of `POST /offsets`, the offset of the zone or why it has none
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum BulkOffset {
+    Offset(ZoneOffset),
+    Error { zone: String, error: String },
+}
+
+#[derive(Deserialize)]
+pub struct OffsetQuery {
+    pub at: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Transition {
+    pub at: String,
+    pub from_offset: String,
+    pub to_offset: String,
+    pub abbreviation: String,
+}
+
+/// `/debug/tz`, the raw zone data behind the other routes
+#[derive(Serialize)]
+pub struct DebugTz {
+    pub tzdb_version: &'static str,
+    pub current: ZoneOffset,
+    pub transitions: Vec<Transition>,
+}
+
+#[derive(Deserialize)]
+pub struct TimezonesQuery {
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+    #[serde(default)]
+    pub count: bool,
+}
+
+#[derive(Serialize)]
+pub struct TimezonesCount {
+    pub total: usize,
+}
+
+#[derive(Deserialize)]
+pub struct TransitionsQuery {
+    pub days: Option<u32>,
+}
+
+/// Calendar period of `/bounds`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    #[default]
+    Day,
+    Month,
+    Year,
+}
+
+#[derive(Deserialize)]
+pub struct BoundsQuery {
+    #[serde(default)]
+    pub period: Period,
+}
+
+/// UTC instants of the local period boundaries, `end` is the start of the
+/// next period and is excluded
+#[derive(Serialize)]
+pub struct Bounds {
+    pub start: String,
+    pub end: String,
+}
+
+#[derive(Deserialize)]
+pub struct IsPastQuery {
+    pub time: String,
+}
+
+#[derive(Serialize)]
+pub struct IsPast {
+    pub is_past: bool,
+    pub seconds_until: i64,
+}
+
+#[derive(Deserialize)]
+pub struct BucketQuery {
+    /// Bucket size in seconds
+    pub interval: i64,
+}
+
+#[derive(Serialize)]
+pub struct Bucket {
+    /// Start of the bucket, Unix time in seconds
+    pub epoch: i64,
+    pub time: Time,
+}
+
+/// Unit of `/now/unix`, from seconds down to nanoseconds
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnixPrecision {
+    #[default]
+    S,
+    Ds,
+    Cs,
+    Ms,
+    Us,
+    Ns,
+}
+
+impl UnixPrecision {
+    fn per_second(self) -> i64 {
+        match self {
+            Self::S => 1,
+            Self::Ds => 10,
+            Self::Cs => 100,
+            Self::Ms => 1_000,
+            Self::Us => 1_000_000,
+            Self::Ns => 1_000_000_000,
+        }
+    }
+
+    /// Whole units since the epoch, `None` past the `i64` range of nanoseconds
+    pub fn count(self, time: DateTime<chrono::Utc>) -> Option<i64> {
+        let per_second = self.per_second();
+        let fraction = i64::from(time.timestamp_subsec_nanos()) / (1_000_000_000 / per_second);
+        time.timestamp()
+            .checked_mul(per_second)?
+            .checked_add(fraction)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UnixQuery {
+    #[serde(default)]
+    pub precision: UnixPrecision,
+}
+
+#[derive(Serialize)]
+pub struct UnixTime {
+    pub epoch: i64,
+    pub precision: UnixPrecision,
+}
+
+#[derive(Deserialize)]
+pub struct EpochQuery {
+    /// Date, RFC 3339 timestamp or Unix seconds, the Unix epoch by default
+    pub base: Option<String>,
+}
+
+/// `/now/epoch`, seconds since a custom epoch
+#[derive(Serialize)]
+pub struct EpochTime {
+    /// Whole seconds since `base`, floored so negative before it
+    pub seconds: i64,
+    /// The base epoch, RFC 3339 in UTC
+    pub base: String,
+}
+
+#[derive(Deserialize)]
+pub struct ZoneStatsQuery {
+    /// How many of the most requested zones to list
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct ZoneCount {
+    pub zone: String,
+    pub count: u64,
+}
+
+/// `/stats/zones`, zones requested since startup
+#[derive(Serialize)]
+pub struct ZoneStats {
+    pub distinct: usize,
+    pub top: Vec<ZoneCount>,
+}
+
+/// `/server-timezone`, the zone of the host rather than of the time served
+#[derive(Serialize)]
+pub struct ServerTimezone {
+    pub timezone: String,
+    /// `TZ`, `system` or `unknown`
+    pub source: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// `/now/formats`, one instant rendered several ways
+#[derive(Serialize)]
+pub struct Formats {
+    /// RFC 3339 in UTC with milliseconds
+    pub iso8601: String,
+    pub rfc2822: String,
+    /// Unix time in seconds
+    pub unix: i64,
+    pub http_date: String,
+    pub components: Time,
+}
+
+#[derive(Deserialize)]
+pub struct SkewQuery {
+    /// Client clock, Unix time in milliseconds
+    pub client_time: i64,
+}
+
+#[derive(Serialize)]
+pub struct Skew {
+    /// Unix time in milliseconds
+    pub server_time: i64,
+    /// Positive when the client clock is ahead
+    pub skew_ms: i64,
+}
+
+#[derive(Deserialize)]
+pub struct CountdownQuery {
+    pub zone: String,
+    /// Local wall clock time in the zone, `YYYY-MM-DDTHH:MM[:SS]`
+    pub target: String,
+}
+
+#[derive(Deserialize)]
+pub struct NextQuery {
+    /// 5 field cron, or 6 / 7 fields starting with the seconds
+    pub cron: String,
+    pub zone: String,
+    /// How many fires to list
+    pub count: Option<usize>,
+}
+
+/// One fire of `/next`
+#[derive(Serialize)]
+pub struct NextFire {
+    pub utc: String,
+    pub local: String,
+    pub interpretation: LocalInterpretation,
+}
+
+#[derive(Serialize)]
+pub struct NextFires {
+    pub cron: String,
+    pub zone: String,
+    pub fires: Vec<NextFire>,
+}
+
+#[derive(Deserialize)]
+pub struct ValidateLocalQuery {
+    pub zone: String,
+    /// Local wall clock time in the zone, `YYYY-MM-DDTHH:MM[:SS]`
+    pub local: String,
+}
+
+#[derive(Deserialize)]
+pub struct OffsetDiffQuery {
+    pub a: String,
+    pub b: String,
+}
+
+/// How far `b` is ahead of `a` right now, negative when it is behind
+#[derive(Debug, PartialEq, Serialize)]
+pub struct OffsetDiff {
+    pub a_offset: String,
+    pub b_offset: String,
+    pub diff_seconds: i64,
+    /// `diff_seconds` as `±H:MM`
+    pub diff: String,
+}
+
+/// Whether a local time exists in a zone, per chrono's `LocalResult`
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LocalStatus {
+    Unique,
+    /// Repeated when clocks go back
+    Ambiguous,
+    /// Skipped when clocks go forward
+    Nonexistent,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct LocalValidation {
+    pub status: LocalStatus,
+    /// Every instant the local time maps to with its offset, earliest first
+    pub candidates: Vec<String>,
+}
+
+/// How a local time was mapped to an instant
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LocalInterpretation {
+    /// The local time exists once
+    Exact,
+    /// The local time repeats when clocks go back, the first occurrence is used
+    Earliest,
+    /// The local time is skipped when clocks go forward, it is moved forward by the gap
+    Shifted,
+}
+
+#[derive(Serialize)]
+pub struct Countdown {
+    pub seconds: i64,
+    pub target_utc: String,
+    pub interpretation: LocalInterpretation,
+}
+
+#[test]
+fn test_calendar_date() {
+    let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+    let julian = |date| match Calendar::Julian.date(date) {
+        Some(CalendarDate::Julian { year, month, day }) => (year, month, day),
+        other => panic!("{other:?}"),
+    };
+
+    // the first Gregorian day followed Julian 1582-10-04
+    assert_eq!(julian(date(1582, 10, 15)), (1582, 10, 5));
+    assert_eq!(julian(date(2026, 10, 14)), (2026, 10, 1));
+    // Julian 2100 is a leap year, Gregorian 2100 is not
+    assert_eq!(julian(date(2100, 3, 14)), (2100, 2, 29));
+    assert_eq!(julian(date(2100, 3, 15)), (2100, 3, 1));
+    // the calendars agree in the 3rd century
+    assert_eq!(julian(date(250, 6, 1)), (250, 6, 1));
+
+    // 2026-01-01 is a Thursday of week 1, 2027-01-01 a Friday of 2026-W53
+    assert_eq!(
+        Calendar::IsoWeek.date(date(2027, 1, 1)),
+        Some(CalendarDate::IsoWeek {
+            year: 2026,
+            week: 53,
+            weekday: 5
+        })
+    );
+    assert_eq!(Calendar::Gregorian.date(date(2027, 1, 1)), None);
+}
+
+#[test]
+fn test_time_julian_day() {
+    use chrono::TimeZone;
+
+    let time = Time::from(chrono::Utc.with_ymd_and_hms(2000, 1, 1, 12, 0, 0).unwrap());
+    assert_eq!(time.julian_day, 2_451_545.0);
+    assert_eq!(time.mjd, 51_544.5);
+
+    // the instant is the same regardless of the zone it is expressed in
+    let time = Time::from(
+        chrono_tz::Asia::Tokyo
+            .with_ymd_and_hms(2000, 1, 1, 21, 0, 0)
+            .unwrap(),
+    );
+    assert_eq!(time.julian_day, 2_451_545.0);
+}
+
+#[test]
+fn test_rounding_rollover() {
+    use chrono::{TimeZone, Utc};
+
+    let time = Utc
+        .with_ymd_and_hms(2026, 12, 31, 23, 59, 59)
+        .unwrap()
+        .with_nanosecond(600_000_000)
+        .unwrap();
+    let truncated = Time::from(Rounding::Truncate.apply(time));
+    assert_eq!((truncated.year, truncated.seconds), (2026, 59));
+
+    let rounded = Time::from(Rounding::Nearest.apply(time));
+    assert_eq!(
+        rounded,
+        Time::from(Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap())
+    );
+
+    let early = time.with_nanosecond(400_000_000).unwrap();
+    assert_eq!(Time::from(Rounding::Nearest.apply(early)).seconds, 59);
+    assert_eq!("Nearest".parse(), Ok(Rounding::Nearest));
+    assert!("up".parse::<Rounding>().is_err());
+}
+
+#[test]
+fn test_time_field_names() {
+    use chrono::{TimeZone, Utc};
+
+    struct Renamed<'a>(&'a Time, &'a FieldNames);
+
+    impl serde::Serialize for Renamed<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.0.serialize_with(self.1, serializer)
+        }
+    }
+
+    let time = Time::from(Utc.with_ymd_and_hms(2026, 10, 14, 8, 30, 15).unwrap());
+    let names: FieldNames = "minutes:min, seconds:sec".parse().unwrap();
+    let value = serde_json::to_value(Renamed(&time, &names)).unwrap();
+    assert_eq!(value["min"], 30);
+    assert_eq!(value["sec"], 15);
+    assert_eq!(value["hour"], 8);
+    assert!(value.get("minutes").is_none());
+
+    let current = serde_json::to_value(Renamed(&time, &FieldNames::default())).unwrap();
+    assert_eq!(current, serde_json::to_value(&time).unwrap());
+
+    assert!("minute:min".parse::<FieldNames>().is_err());
+    assert!("minutes".parse::<FieldNames>().is_err());
+    assert!("minutes:hour".parse::<FieldNames>().is_err());
+    assert!("minutes:".parse::<FieldNames>().is_err());
+    assert!("minutes:min,minutes:m".parse::<FieldNames>().is_err());
+    assert!("minutes:calendar".parse::<FieldNames>().is_err());
+    assert!("tz:astro".parse::<FieldNames>().is_err());
+}
+
+#[test]
+fn test_time_precision() {
+    use chrono::{TimeZone, Utc};
+
+    let time = Utc
+        .with_ymd_and_hms(2026, 10, 14, 8, 30, 15)
+        .unwrap()
+        .with_nanosecond(123_456_789)
+        .unwrap();
+    let value =
+        serde_json::to_value(Time::from(time).with_precision(Some(Precision::Seconds))).unwrap();
+    assert!(value.get("millis").is_none());
+    assert!(value.get("nanos").is_none());
+
+    let value =
+        serde_json::to_value(Time::from(time).with_precision(Some(Precision::Millis))).unwrap();
+    assert_eq!(value["millis"], 123);
+    assert!(value.get("nanos").is_none());
+
+    let value =
+        serde_json::to_value(Time::from(time).with_precision(Some(Precision::Nanos))).unwrap();
+    assert_eq!(value["millis"], 123);
+    assert_eq!(value["nanos"], 123_456_789);
+}
+
+#[test]
+fn test_time_key_order() {
+    use chrono::{TimeZone, Utc};
+
+    struct Renamed<'a>(&'a Time, &'a FieldNames);
+
+    impl serde::Serialize for Renamed<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.0.serialize_with(self.1, serializer)
+        }
+    }
+
+    /// Keys of a JSON object in document order, which `serde_json::Value` loses
+    fn keys(json: &str) -> Vec<String> {
+        struct Keys;
+
+        impl<'de> serde::de::Visitor<'de> for Keys {
+            type Value = Vec<String>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("an object")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut keys = Vec::new();
+                while let Some((key, _)) = map.next_entry::<String, serde::de::IgnoredAny>()? {
+                    keys.push(key);
+                }
+                Ok(keys)
+            }
+        }
+
+        serde::Deserializer::deserialize_map(&mut serde_json::Deserializer::from_str(json), Keys)
+            .unwrap()
+    }
+
+    let time = Utc
+        .with_ymd_and_hms(2026, 10, 14, 8, 30, 15)
+        .unwrap()
+        .with_nanosecond(123_456_789)
+        .unwrap();
+
+    let json =
+        serde_json::to_string(&Time::from(time).with_precision(Some(Precision::Seconds))).unwrap();
+    assert_eq!(
+        keys(&json),
+        [
+            "year",
+            "month",
+            "day",
+            "hour",
+            "minutes",
+            "seconds",
+            "julian_day",
+            "mjd",
+            "tz"
+        ]
+    );
+
+    let json =
+        serde_json::to_string(&Time::from(time).with_precision(Some(Precision::Millis))).unwrap();
+    assert_eq!(
+        keys(&json),
+        [
+            "year",
+            "month",
+            "day",
+            "hour",
+            "minutes",
+            "seconds",
+            "millis",
+            "julian_day",
+            "mjd",
+            "tz"
+        ]
+    );
+
+    let json =
+        serde_json::to_string(&Time::from(time).with_precision(Some(Precision::Nanos))).unwrap();
+    assert_eq!(keys(&json), TIME_FIELDS);
+
+    let names: FieldNames = "year:y, tz:zone".parse().unwrap();
+    let renamed = Time::from(time).with_precision(Some(Precision::Nanos));
+    let json = serde_json::to_string(&Renamed(&renamed, &names)).unwrap();
+    let mut expected = TIME_FIELDS.map(str::to_owned);
+    expected[0] = "y".to_owned();
+    expected[10] = "zone".to_owned();
+    assert_eq!(keys(&json), expected);
+}
+
+#[test]
+fn test_time_zone_well_formed() {
+    let zone = |continent: &str, region: &str| TimeZone {
+        continent: continent.to_owned(),
+        region: region.to_owned(),
+    };
+
+    assert!(zone("Europe", "Atlantis").is_well_formed());
+    assert!(zone("Etc", "GMT+5").is_well_formed());
+    assert!(zone("America", "Port-au-Prince").is_well_formed());
+    assert!(!zone("Europe", "").is_well_formed());
+    assert!(!zone("Europe", "Ber lin").is_well_formed());
+    assert!(!zone("Europe", "Berlin;drop").is_well_formed());
+    assert!(!zone("Europe", &"a".repeat(65)).is_well_formed());
+
+    for tz in chrono_tz::TZ_VARIANTS {
+        if let Some((continent, region)) = tz.name().split_once('/') {
+            if !region.contains('/') {
+                assert!(zone(continent, region).is_well_formed(), "{tz}");
+            }
+        }
+    }
+}
+
+#[test]
+fn test_unix_precision_count() {
+    let time = DateTime::from_timestamp(1_700_000_000, 123_456_789).unwrap();
+    let counts: Vec<_> = [
+        UnixPrecision::S,
+        UnixPrecision::Ds,
+        UnixPrecision::Cs,
+        UnixPrecision::Ms,
+        UnixPrecision::Us,
+        UnixPrecision::Ns,
+    ]
+    .iter()
+    .map(|precision| precision.count(time).unwrap())
+    .collect();
+    assert_eq!(
+        counts,
+        [
+            1_700_000_000,
+            17_000_000_001,
+            170_000_000_012,
+            1_700_000_000_123,
+            1_700_000_000_123_456,
+            1_700_000_000_123_456_789,
+        ]
+    );
+
+    // before the epoch the count is floored like the seconds
+    let before = DateTime::from_timestamp(-1, 950_000_000).unwrap();
+    assert_eq!(UnixPrecision::Ds.count(before), Some(-1));
+    assert_eq!(
+        UnixPrecision::Ns.count(DateTime::<chrono::Utc>::MAX_UTC),
+        None
+    );
+}
@@ -0,0 +1,40 @@
+use std::io;
+
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum NtpError {
+    #[error("connection to ntp failed : {0}")]
+    Connection(String),
+    #[error("ntp request timed out")]
+    Timeout,
+    #[error("invalid time from ntp : {0}")]
+    InvalidTime(i64),
+    #[error("ntp timestamp {0} is out of the unix epoch range")]
+    EpochOutOfRange(u32),
+    #[error("ntp time {0} is more than {1} days away from the system clock")]
+    Implausible(DateTime<Utc>, i64),
+    #[error("ntp request panicked : {0}")]
+    Panic(String),
+    #[error("ntp retry budget exhausted")]
+    BudgetExhausted,
+    #[error("ntp server polled {0:?} ago, under the minimum poll interval")]
+    PollDeferred(std::time::Duration),
+}
+
+impl From<ntp::errors::Error> for NtpError {
+    fn from(err: ntp::errors::Error) -> Self {
+        match err.kind() {
+            ntp::errors::ErrorKind::IOError(io_err)
+                if matches!(
+                    io_err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                Self::Timeout
+            }
+            _ => Self::Connection(err.to_string()),
+        }
+    }
+}
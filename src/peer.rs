@@ -0,0 +1,64 @@
+//! Sibling node queried for the time when every NTP server failed
+
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_derive::Deserialize;
+use thiserror::Error;
+
+/// Upper bound for the whole peer exchange
+const PEER_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Error)]
+pub enum PeerError {
+    #[error("peer request failed : {0}")]
+    Request(String),
+    #[error("peer time isn't NTP synced (source {0})")]
+    Unsynced(String),
+    #[error("invalid time from peer")]
+    InvalidTime,
+}
+
+#[derive(Deserialize)]
+struct PeerTime {
+    year: i32,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minutes: u32,
+    seconds: u32,
+}
+
+#[derive(Deserialize)]
+struct PeerMeta {
+    source: String,
+}
+
+#[derive(Deserialize)]
+struct PeerEnvelope {
+    data: PeerTime,
+    meta: PeerMeta,
+}
+
+/// Read the UTC time of the peer `/now`, blocking. Only an answer the peer
+/// itself got from NTP is accepted, its system clock is no better than ours.
+/// The peer must serve the default field names.
+pub fn fetch(url: &str) -> Result<DateTime<Utc>, PeerError> {
+    let url = format!("{}/now?envelope=true", url.trim_end_matches('/'));
+    let envelope: PeerEnvelope = ureq::get(&url)
+        .timeout(PEER_TIMEOUT)
+        .call()
+        .map_err(|err| PeerError::Request(err.to_string()))?
+        .into_json()
+        .map_err(|err| PeerError::Request(err.to_string()))?;
+
+    if envelope.meta.source != "ntp" {
+        return Err(PeerError::Unsynced(envelope.meta.source));
+    }
+
+    let time = envelope.data;
+    NaiveDate::from_ymd_opt(time.year, time.month, time.day)
+        .and_then(|date| date.and_hms_opt(time.hour, time.minutes, time.seconds))
+        .map(|time| time.and_utc())
+        .ok_or(PeerError::InvalidTime)
+}
@@ -0,0 +1,350 @@
+//! POSIX `TZ` strings such as `EST5EDT,M3.2.0,M11.1.0`, for clients that
+//! can't carry the IANA database
+
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, TimeDelta, Utc, Weekday};
+
+/// Rules used when a zone has a DST name but no rule, the US rules like glibc
+const DEFAULT_RULES: &str = "M3.2.0,M11.1.0";
+/// Transition time when a rule has none, 02:00 local
+const DEFAULT_TRANSITION_TIME: i64 = 2 * 3600;
+/// Largest offset accepted, 24:59:59 per POSIX
+const MAX_OFFSET: i64 = 24 * 3600 + 59 * 60 + 59;
+/// Largest transition time accepted, 167:59:59 per RFC 8536
+const MAX_TRANSITION_TIME: i64 = 167 * 3600 + 59 * 60 + 59;
+
+/// Day of the year a transition happens on
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RuleDay {
+    /// `Jn`, 1 to 365 never counting February 29
+    Julian(u16),
+    /// `n`, 0 to 365 counting February 29
+    ZeroBased(u16),
+    /// `Mm.w.d`, day `d` (0 is Sunday) of week `w` (5 is the last) of month `m`
+    MonthWeekDay(u32, u32, u32),
+}
+
+impl RuleDay {
+    fn date(self, year: i32) -> Option<NaiveDate> {
+        match self {
+            Self::Julian(day) => {
+                let date =
+                    NaiveDate::from_ymd_opt(year, 1, 1)? + TimeDelta::days(i64::from(day) - 1);
+                // day 60 is always March 1st
+                if date.leap_year() && date.ordinal() >= 60 {
+                    date.succ_opt()
+                } else {
+                    Some(date)
+                }
+            }
+            Self::ZeroBased(day) => NaiveDate::from_yo_opt(year, u32::from(day) + 1),
+            Self::MonthWeekDay(month, 5, day) => {
+                let next_month = if month == 12 {
+                    NaiveDate::from_ymd_opt(year + 1, 1, 1)?
+                } else {
+                    NaiveDate::from_ymd_opt(year, month + 1, 1)?
+                };
+                let last = next_month.pred_opt()?;
+                let back = (last.weekday().num_days_from_sunday() + 7 - day) % 7;
+                Some(last - TimeDelta::days(back.into()))
+            }
+            Self::MonthWeekDay(month, week, day) => {
+                let weekday = Weekday::try_from(((day + 6) % 7) as u8).ok()?;
+                NaiveDate::from_weekday_of_month_opt(year, month, weekday, week as u8)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Rule {
+    day: RuleDay,
+    /// Seconds after local midnight, may be negative or past 24h
+    time: i64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Dst {
+    name: String,
+    /// Seconds east of UTC
+    offset: i64,
+    start: Rule,
+    end: Rule,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PosixTz {
+    name: String,
+    /// Seconds east of UTC, the opposite of the POSIX sign
+    offset: i64,
+    dst: Option<Dst>,
+}
+
+/// Cursor over the TZ string
+struct Parser<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn eat(&mut self, c: char) -> bool {
+        match self.rest.strip_prefix(c) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn take_while(&mut self, pred: impl Fn(char) -> bool) -> &'a str {
+        let end = self.rest.find(|c| !pred(c)).unwrap_or(self.rest.len());
+        let (taken, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        taken
+    }
+
+    /// `EST` or the quoted `<+0330>` form, at least 3 characters
+    fn name(&mut self) -> Option<String> {
+        let name = if self.eat('<') {
+            let name = self.take_while(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-');
+            self.eat('>').then_some(name)?
+        } else {
+            self.take_while(|c| c.is_ascii_alphabetic())
+        };
+        (name.len() >= 3).then(|| name.to_owned())
+    }
+
+    fn number(&mut self) -> Option<i64> {
+        let digits = self.take_while(|c| c.is_ascii_digit());
+        if digits.is_empty() || digits.len() > 3 {
+            return None;
+        }
+        digits.parse().ok()
+    }
+
+    /// `[+-]hh[:mm[:ss]]` in seconds
+    fn duration(&mut self, max: i64) -> Option<i64> {
+        let sign = if self.eat('-') {
+            -1
+        } else {
+            self.eat('+');
+            1
+        };
+        let mut seconds = self.number()? * 3600;
+        for unit in [60, 1] {
+            if !self.eat(':') {
+                break;
+            }
+            let part = self.number().filter(|part| *part < 60)?;
+            seconds += part * unit;
+        }
+        (seconds <= max).then_some(sign * seconds)
+    }
+
+    fn rule(&mut self) -> Option<Rule> {
+        let day = if self.eat('J') {
+            RuleDay::Julian(self.number().filter(|day| (1..=365).contains(day))? as u16)
+        } else if self.eat('M') {
+            let month = self.number().filter(|month| (1..=12).contains(month))?;
+            self.eat('.').then_some(())?;
+            let week = self.number().filter(|week| (1..=5).contains(week))?;
+            self.eat('.').then_some(())?;
+            let day = self.number().filter(|day| (0..=6).contains(day))?;
+            RuleDay::MonthWeekDay(month as u32, week as u32, day as u32)
+        } else {
+            RuleDay::ZeroBased(self.number().filter(|day| (0..=365).contains(day))? as u16)
+        };
+        let time = if self.eat('/') {
+            self.duration(MAX_TRANSITION_TIME)?
+        } else {
+            DEFAULT_TRANSITION_TIME
+        };
+        Some(Rule { day, time })
+    }
+
+    fn rules(&mut self) -> Option<(Rule, Rule)> {
+        let start = self.rule()?;
+        self.eat(',').then_some(())?;
+        Some((start, self.rule()?))
+    }
+}
+
+impl FromStr for PosixTz {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid POSIX TZ string {s:?}");
+        let mut parser = Parser { rest: s.trim() };
+
+        let name = parser.name().ok_or_else(invalid)?;
+        let offset = -parser.duration(MAX_OFFSET).ok_or_else(invalid)?;
+        if parser.rest.is_empty() {
+            return Ok(Self {
+                name,
+                offset,
+                dst: None,
+            });
+        }
+
+        let dst_name = parser.name().ok_or_else(invalid)?;
+        let dst_offset = if parser.rest.is_empty() || parser.rest.starts_with(',') {
+            offset + 3600
+        } else {
+            -parser.duration(MAX_OFFSET).ok_or_else(invalid)?
+        };
+        let (start, end) = if parser.eat(',') {
+            parser.rules()
+        } else {
+            Parser {
+                rest: DEFAULT_RULES,
+            }
+            .rules()
+        }
+        .ok_or_else(invalid)?;
+        if !parser.rest.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            name,
+            offset,
+            dst: Some(Dst {
+                name: dst_name,
+                offset: dst_offset,
+                start,
+                end,
+            }),
+        })
+    }
+}
+
+impl PosixTz {
+    /// Offset and abbreviation in effect at `time`
+    pub fn offset_at(&self, time: DateTime<Utc>) -> Option<(FixedOffset, &str)> {
+        let (offset, name) = match &self.dst {
+            Some(dst) if dst.is_active(time, self.offset)? => (dst.offset, dst.name.as_str()),
+            _ => (self.offset, self.name.as_str()),
+        };
+        Some((FixedOffset::east_opt(offset as i32)?, name))
+    }
+
+    pub fn localize(&self, time: DateTime<Utc>) -> Option<(DateTime<FixedOffset>, &str)> {
+        let (offset, name) = self.offset_at(time)?;
+        Some((time.with_timezone(&offset), name))
+    }
+}
+
+impl Dst {
+    /// Whether DST is in effect at `time`, the start is given in standard time
+    /// and the end in DST, the southern hemisphere has the end first
+    fn is_active(&self, time: DateTime<Utc>, std_offset: i64) -> Option<bool> {
+        let year = (time + TimeDelta::seconds(std_offset)).year();
+        let transition = |rule: Rule, offset: i64| {
+            let midnight = rule.day.date(year)?.and_hms_opt(0, 0, 0)?.and_utc();
+            Some(midnight + TimeDelta::seconds(rule.time - offset))
+        };
+        let start = transition(self.start, std_offset)?;
+        let end = transition(self.end, self.offset)?;
+        Some(if start < end {
+            start <= time && time < end
+        } else {
+            time < end || start <= time
+        })
+    }
+}
+
+#[test]
+fn test_parse() {
+    let tz: PosixTz = "EST5EDT,M3.2.0,M11.1.0".parse().unwrap();
+    assert_eq!(tz.offset, -5 * 3600);
+    let dst = tz.dst.unwrap();
+    assert_eq!((dst.name.as_str(), dst.offset), ("EDT", -4 * 3600));
+    assert_eq!(
+        dst.start,
+        Rule {
+            day: RuleDay::MonthWeekDay(3, 2, 0),
+            time: DEFAULT_TRANSITION_TIME
+        }
+    );
+
+    let tz: PosixTz = "<+0330>-3:30".parse().unwrap();
+    assert_eq!(
+        (tz.name.as_str(), tz.offset, tz.dst),
+        ("+0330", 12600, None)
+    );
+
+    // no rules means the US ones
+    assert_eq!(
+        "EST5EDT".parse::<PosixTz>().unwrap(),
+        "EST5EDT,M3.2.0/2,M11.1.0/2".parse().unwrap()
+    );
+
+    for invalid in [
+        "",
+        "E5",
+        "EST",
+        "EST5EDT,M13.1.0,M11.1.0",
+        "EST5EDT,M3.2.0",
+        "EST99",
+        "EST5x",
+    ] {
+        assert!(invalid.parse::<PosixTz>().is_err(), "{invalid}");
+    }
+}
+
+#[test]
+fn test_offset_at() {
+    use chrono::TimeZone;
+
+    let at = |y, m, d, h, min| Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap();
+    let offset = |tz: &PosixTz, time| {
+        let (offset, name) = tz.offset_at(time).unwrap();
+        (offset.local_minus_utc() / 3600, name.to_owned())
+    };
+
+    // US Eastern switches at 07:00 UTC on 2024-03-10 and 06:00 UTC on 2024-11-03
+    let eastern: PosixTz = "EST5EDT,M3.2.0,M11.1.0".parse().unwrap();
+    assert_eq!(
+        offset(&eastern, at(2024, 3, 10, 6, 59)),
+        (-5, "EST".to_owned())
+    );
+    assert_eq!(
+        offset(&eastern, at(2024, 3, 10, 7, 0)),
+        (-4, "EDT".to_owned())
+    );
+    assert_eq!(
+        offset(&eastern, at(2024, 11, 3, 5, 59)),
+        (-4, "EDT".to_owned())
+    );
+    assert_eq!(
+        offset(&eastern, at(2024, 11, 3, 6, 0)),
+        (-5, "EST".to_owned())
+    );
+
+    // central Europe switches on the last Sundays at 01:00 UTC
+    let berlin: PosixTz = "CET-1CEST,M3.5.0,M10.5.0/3".parse().unwrap();
+    assert_eq!(
+        offset(&berlin, at(2024, 3, 31, 0, 59)),
+        (1, "CET".to_owned())
+    );
+    assert_eq!(
+        offset(&berlin, at(2024, 3, 31, 1, 0)),
+        (2, "CEST".to_owned())
+    );
+    assert_eq!(
+        offset(&berlin, at(2024, 10, 27, 1, 0)),
+        (1, "CET".to_owned())
+    );
+
+    // southern hemisphere, DST over the new year
+    let sydney: PosixTz = "AEST-10AEDT,M10.1.0,M4.1.0/3".parse().unwrap();
+    assert_eq!(
+        offset(&sydney, at(2024, 1, 15, 0, 0)),
+        (11, "AEDT".to_owned())
+    );
+    assert_eq!(
+        offset(&sydney, at(2024, 7, 1, 0, 0)),
+        (10, "AEST".to_owned())
+    );
+}
@@ -0,0 +1,84 @@
+//! Bodies that only change with the binary, gzip compressed once instead of by
+//! the `Compress` middleware on every request
+
+use std::io::Write;
+
+use actix_web::{
+    http::header::{self, ContentEncoding, ContentType, Encoding},
+    web::Bytes,
+    HttpMessage, HttpRequest, HttpResponse, HttpResponseBuilder,
+};
+use flate2::{write::GzEncoder, Compression};
+use serde::Serialize;
+
+/// JSON body with its gzip encoding
+pub struct Precompressed {
+    body: Bytes,
+    gzip: Bytes,
+}
+
+impl Precompressed {
+    pub fn json(value: &impl Serialize) -> Self {
+        let body = serde_json::to_vec(value).expect("JSON body");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&body).expect("gzip into memory");
+        Self {
+            body: body.into(),
+            gzip: encoder.finish().expect("gzip into memory").into(),
+        }
+    }
+
+    /// True when the `Accept-Encoding` of `req` prefers gzip over identity
+    pub fn accepts_gzip(req: &HttpRequest) -> bool {
+        req.get_header::<header::AcceptEncoding>()
+            .and_then(|accept| accept.negotiate([Encoding::gzip(), Encoding::identity()].iter()))
+            == Some(Encoding::gzip())
+    }
+
+    /// Finish `res` with the gzip body when the client accepts it, the encoding
+    /// header also keeps the `Compress` middleware off the response
+    pub fn respond(&self, req: &HttpRequest, mut res: HttpResponseBuilder) -> HttpResponse {
+        res.content_type(ContentType::json())
+            .insert_header((header::VARY, "Accept-Encoding"));
+        if Self::accepts_gzip(req) {
+            res.insert_header(ContentEncoding::Gzip)
+                .body(self.gzip.clone())
+        } else {
+            res.body(self.body.clone())
+        }
+    }
+}
+
+#[test]
+fn test_precompressed() {
+    use actix_web::test::TestRequest;
+    use std::io::Read;
+
+    let names = vec!["Europe/Berlin"; 100];
+    let body = Precompressed::json(&names);
+    assert_eq!(&body.body[..], serde_json::to_vec(&names).unwrap());
+    assert!(body.gzip.len() < body.body.len());
+    let mut decoded = Vec::new();
+    flate2::read::GzDecoder::new(&body.gzip[..])
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, body.body);
+
+    for (accept, gzip) in [
+        (Some("gzip, deflate, br"), true),
+        (Some("br;q=1, gzip;q=0.5"), true),
+        (Some("gzip;q=0"), false),
+        (Some("br"), false),
+        (None, false),
+    ] {
+        let mut req = TestRequest::get();
+        if let Some(accept) = accept {
+            req = req.insert_header((header::ACCEPT_ENCODING, accept));
+        }
+        assert_eq!(
+            Precompressed::accepts_gzip(&req.to_http_request()),
+            gzip,
+            "{accept:?}"
+        );
+    }
+}
@@ -0,0 +1,89 @@
+//! Token bucket shared by every request bounding the NTP attempts per minute,
+//! so a flood of cache misses during an outage doesn't retry on each of them
+
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde_derive::Serialize;
+
+const REFILL_PERIOD: Duration = Duration::from_secs(60);
+
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+pub struct RetryBudget {
+    /// Attempts per minute, also the burst size
+    capacity: u32,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct RetryBudgetStatus {
+    pub per_minute: u32,
+    pub available: u32,
+}
+
+impl RetryBudget {
+    pub fn new(per_minute: u32) -> Self {
+        Self {
+            capacity: per_minute,
+            bucket: Mutex::new(Bucket {
+                tokens: f64::from(per_minute),
+                refilled: Instant::now(),
+            }),
+        }
+    }
+
+    /// Refill continuously, `capacity` tokens per `REFILL_PERIOD`
+    fn refill(&self, bucket: &mut Bucket, now: Instant) {
+        let elapsed = now.saturating_duration_since(bucket.refilled);
+        let refill = elapsed.as_secs_f64() / REFILL_PERIOD.as_secs_f64() * f64::from(self.capacity);
+        bucket.tokens = (bucket.tokens + refill).min(f64::from(self.capacity));
+        bucket.refilled = now;
+    }
+
+    fn try_acquire_at(&self, now: Instant) -> bool {
+        let mut bucket = self.bucket.lock().unwrap();
+        self.refill(&mut bucket, now);
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+
+    /// Take one attempt, `false` when the budget is exhausted
+    pub fn try_acquire(&self) -> bool {
+        self.try_acquire_at(Instant::now())
+    }
+
+    pub fn status(&self) -> RetryBudgetStatus {
+        let mut bucket = self.bucket.lock().unwrap();
+        self.refill(&mut bucket, Instant::now());
+        RetryBudgetStatus {
+            per_minute: self.capacity,
+            available: bucket.tokens as u32,
+        }
+    }
+}
+
+#[test]
+fn test_retry_budget_refill() {
+    let budget = RetryBudget::new(2);
+    let start = budget.bucket.lock().unwrap().refilled;
+
+    assert!(budget.try_acquire_at(start));
+    assert!(budget.try_acquire_at(start));
+    assert!(!budget.try_acquire_at(start));
+    // one token every 30s
+    assert!(!budget.try_acquire_at(start + Duration::from_secs(29)));
+    assert!(budget.try_acquire_at(start + Duration::from_secs(31)));
+    // never more than the capacity
+    assert!(budget.try_acquire_at(start + Duration::from_secs(3600)));
+    assert!(budget.try_acquire_at(start + Duration::from_secs(3600)));
+    assert!(!budget.try_acquire_at(start + Duration::from_secs(3600)));
+}
@@ -0,0 +1,109 @@
+//! `--check` smoke test run instead of the server, for CI and deploy gates
+
+use chrono::Utc;
+
+use crate::{app_context::AppContext, config::Config, models, timezone};
+
+/// Zones parsed the way the `{continent}/{region}` routes do
+const CHECK_ZONES: [(&str, &str); 2] = [("europe", "berlin"), ("america", "new_york")];
+
+pub struct Check {
+    pub name: &'static str,
+    /// What was verified, or why it failed
+    pub result: Result<String, String>,
+}
+
+/// Context with just what the checks use, no peer so only NTP can pass
+pub fn check_context(config: &Config) -> AppContext {
+    AppContext::new(config.cache_timeout, config.ntp_server.clone())
+        .with_ntp_version(config.ntp_version)
+        .with_sanity_window(config.sanity_window_days)
+}
+
+async fn check_ntp(app: &AppContext) -> Result<String, String> {
+    app.get_time().await;
+    let status = app.ntp_status().await;
+    if status.synced {
+        Ok(format!("synced from {}", status.server))
+    } else {
+        Err("no NTP server answered with a plausible time".to_owned())
+    }
+}
+
+fn check_zones() -> Result<String, String> {
+    let names = CHECK_ZONES
+        .iter()
+        .map(|(continent, region)| {
+            let args = models::TimeZone {
+                continent: continent.to_string(),
+                region: region.to_string(),
+            };
+            let tz = args
+                .to_tz()
+                .map_err(|err| format!("{continent}/{region} : {err}"))?;
+            let offset = timezone::zone_offset(&tz, Utc::now());
+            Ok(format!("{} {}", offset.zone, offset.offset))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(names.join(", "))
+}
+
+fn check_serialize() -> Result<String, String> {
+    let time = models::Time::from(Utc::now()).with_tz("UTC");
+    let body = serde_json::to_string(&time).map_err(|err| err.to_string())?;
+    Ok(format!("{} bytes", body.len()))
+}
+
+pub async fn run(app: &AppContext) -> Vec<Check> {
+    vec![
+        Check {
+            name: "ntp",
+            result: check_ntp(app).await,
+        },
+        Check {
+            name: "zones",
+            result: check_zones(),
+        },
+        Check {
+            name: "serialize",
+            result: check_serialize(),
+        },
+    ]
+}
+
+/// Print every check, true when all of them passed
+pub fn report(checks: &[Check]) -> bool {
+    for check in checks {
+        match &check.result {
+            Ok(detail) => println!("ok   {} : {detail}", check.name),
+            Err(err) => println!("FAIL {} : {err}", check.name),
+        }
+    }
+    checks.iter().all(|check| check.result.is_ok())
+}
+
+#[tokio::test]
+async fn test_self_check() {
+    let ntp = crate::mock_ntp::MockNtpServer::start(Utc::now());
+    let app = AppContext::new(60, ntp.address());
+    let checks = run(&app).await;
+    assert!(report(&checks));
+    assert_eq!(
+        checks.iter().map(|check| check.name).collect::<Vec<_>>(),
+        ["ntp", "zones", "serialize"]
+    );
+    assert!(checks[1]
+        .result
+        .as_ref()
+        .is_ok_and(|zones| zones.contains("America/New_York")));
+
+    // an implausible NTP time fails the gate, the other checks still run
+    let ntp = crate::mock_ntp::MockNtpServer::start(
+        chrono::TimeZone::with_ymd_and_hms(&Utc, 1990, 1, 1, 0, 0, 0).unwrap(),
+    );
+    let app = AppContext::new(60, ntp.address()).with_sanity_window(30);
+    let checks = run(&app).await;
+    assert!(!report(&checks));
+    assert!(checks[0].result.is_err());
+    assert!(checks[1..].iter().all(|check| check.result.is_ok()));
+}
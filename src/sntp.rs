@@ -0,0 +1,280 @@
+//! SNTP client exchange, the `ntp` crate always asks as version 2 so the
+//! client packet is built here with the configured version and the exchange
+//! timestamps needed for the offset and delay estimate
+
+use std::{
+    io,
+    net::{Ipv6Addr, ToSocketAddrs, UdpSocket},
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use chrono::{DateTime, Utc};
+use ntp::{
+    errors,
+    formats::{timestamp::TimestampFormat, Mode, Version},
+    packet::Packet,
+};
+use serde_derive::Serialize;
+
+use crate::app_context::NTP_UNIX_OFFSET;
+
+const SOCKET_TIMEOUT: Duration = Duration::from_secs(5);
+const PACKET_SIZE: usize = 48;
+
+/// Protocol version announced in requests, NTPv4 (RFC 5905) unless a legacy
+/// server only answers NTPv3 (RFC 1305) clients
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub enum NtpVersion {
+    #[serde(rename = "3")]
+    V3,
+    #[default]
+    #[serde(rename = "4")]
+    V4,
+}
+
+impl FromStr for NtpVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim_start_matches(['v', 'V']) {
+            "3" => Ok(Self::V3),
+            "4" => Ok(Self::V4),
+            _ => Err(format!("Unsupported NTP version {s}, use 3 or 4")),
+        }
+    }
+}
+
+impl From<NtpVersion> for Version {
+    fn from(version: NtpVersion) -> Self {
+        match version {
+            NtpVersion::V3 => Version::Ver3,
+            NtpVersion::V4 => Version::Ver4,
+        }
+    }
+}
+
+/// Client mode request, `transmit` comes back as the origin timestamp of the
+/// response
+pub fn client_packet(version: NtpVersion, transmit: TimestampFormat) -> Vec<u8> {
+    let mut packet = Packet::new_client();
+    packet.vn = version.into();
+    packet.transmit_time = transmit;
+    packet.into()
+}
+
+/// NTP timestamp of a system time, the `ntp` crate scales the fraction by
+/// 1e10 instead of 1e9 so it isn't used for the exchange timestamps
+fn timestamp(time: SystemTime) -> TimestampFormat {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    TimestampFormat {
+        sec: (since_epoch.as_secs() as i64 + NTP_UNIX_OFFSET) as u32,
+        frac: ((u64::from(since_epoch.subsec_nanos()) << 32) / 1_000_000_000) as u32,
+    }
+}
+
+/// Seconds since the NTP epoch
+fn seconds(timestamp: &TimestampFormat) -> f64 {
+    f64::from(timestamp.sec) + f64::from(timestamp.frac) / 4_294_967_296.0
+}
+
+/// Clock offset and round-trip delay of one exchange, in seconds
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Estimate {
+    pub offset: f64,
+    pub delay: f64,
+}
+
+impl Estimate {
+    /// RFC 4330 from the client transmit `t1`, server receive `t2`, server
+    /// transmit `t3` and client receive `t4` times
+    pub fn new(t1: f64, t2: f64, t3: f64, t4: f64) -> Self {
+        Self {
+            offset: ((t2 - t1) + (t3 - t4)) / 2.0,
+            delay: (t4 - t1) - (t3 - t2),
+        }
+    }
+}
+
+/// Blocking request/response exchange with the server, with the client
+/// transmit and receive times
+fn exchange(
+    address: String,
+    version: NtpVersion,
+) -> errors::Result<(Packet, TimestampFormat, TimestampFormat)> {
+    let address = address.to_socket_addrs()?.next().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, format!("no address for {address}"))
+    })?;
+    // the socket has to be of the family of the server
+    let socket = UdpSocket::bind(if address.is_ipv6() {
+        "[::]:0"
+    } else {
+        "0.0.0.0:0"
+    })?;
+    socket.set_read_timeout(Some(SOCKET_TIMEOUT))?;
+    socket.set_write_timeout(Some(SOCKET_TIMEOUT))?;
+    let sent = timestamp(SystemTime::now());
+    socket.send_to(&client_packet(version, sent), address)?;
+
+    let mut buf = [0u8; PACKET_SIZE];
+    socket.recv(&mut buf)?;
+    let received = timestamp(SystemTime::now());
+    let packet = Packet::try_from(&buf[..])?;
+    // a late answer to an earlier request or a forged one doesn't echo ours
+    if packet.orig_time != sent {
+        return Err("ntp reply origin timestamp doesn't match the request".into());
+    }
+    if packet.mode != Mode::Server {
+        return Err(format!("ntp reply in {:?} mode instead of server", packet.mode).into());
+    }
+    Ok((packet, sent, received))
+}
+
+pub fn request(address: String, version: NtpVersion) -> errors::Result<Packet> {
+    exchange(address, version).map(|(packet, _, _)| packet)
+}
+
+/// Offset of the server clock from ours and the network delay, from all four
+/// timestamps of an exchange, with the response
+pub fn estimate(address: String, version: NtpVersion) -> errors::Result<(Packet, Estimate)> {
+    let (packet, sent, received) = exchange(address, version)?;
+    let estimate = Estimate::new(
+        seconds(&sent),
+        seconds(&packet.recv_time),
+        seconds(&packet.transmit_time),
+        seconds(&received),
+    );
+    Ok((packet, estimate))
+}
+
+/// UTC instant of an NTP timestamp of era 0, `None` before the Unix epoch
+pub fn unix_time(timestamp: &TimestampFormat) -> Option<DateTime<Utc>> {
+    let secs = i64::from(timestamp.sec) - NTP_UNIX_OFFSET;
+    let nanos = (u64::from(timestamp.frac) * 1_000_000_000) >> 32;
+    if secs < 0 {
+        return None;
+    }
+    DateTime::from_timestamp(secs, nanos as u32)
+}
+
+/// 64-bit wire form of a timestamp, as shown by packet captures
+pub fn raw_timestamp(timestamp: &TimestampFormat) -> u64 {
+    (u64::from(timestamp.sec) << 32) | u64::from(timestamp.frac)
+}
+
+/// Validate a `host[:port]` server address, the port defaults to 123 and IPv6
+/// literals are bracketed as in `[::1]:123`
+pub fn parse_server(server: &str) -> Option<String> {
+    let (host, port) = match server.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => (host, port.parse::<u16>().ok()?),
+        _ => (server, 123),
+    };
+    let valid = if let Some(ip) = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+    {
+        ip.parse::<Ipv6Addr>().is_ok()
+    } else {
+        !host.is_empty()
+            && host.len() <= 253
+            && host.split('.').all(|label| {
+                !label.is_empty()
+                    && label.len() <= 63
+                    && !label.starts_with('-')
+                    && !label.ends_with('-')
+                    && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            })
+    };
+    (valid && port != 0).then(|| format!("{host}:{port}"))
+}
+
+#[test]
+fn test_client_packet_version() {
+    // LI (2 bits) | VN (3 bits) | Mode (3 bits), client mode is 3
+    let transmit = timestamp(SystemTime::now());
+    assert_eq!(client_packet(NtpVersion::V4, transmit)[0], (4 << 3) | 3);
+    assert_eq!(client_packet(NtpVersion::V3, transmit)[0], (3 << 3) | 3);
+    assert_eq!(
+        client_packet(NtpVersion::default(), transmit).len(),
+        PACKET_SIZE
+    );
+
+    assert_eq!("3".parse(), Ok(NtpVersion::V3));
+    assert_eq!("v4".parse(), Ok(NtpVersion::V4));
+    assert!("2".parse::<NtpVersion>().is_err());
+}
+
+#[test]
+fn test_estimate_math() {
+    // server 0.5s ahead, 20ms each way and 10ms of processing
+    let estimate = Estimate::new(100.0, 100.52, 100.53, 100.05);
+    assert!((estimate.offset - 0.5).abs() < 1e-9);
+    assert!((estimate.delay - 0.04).abs() < 1e-9);
+
+    let time = UNIX_EPOCH + std::time::Duration::from_millis(1_500);
+    assert_eq!(
+        seconds(&timestamp(time)),
+        (1 + NTP_UNIX_OFFSET) as f64 + 0.5
+    );
+}
+
+#[test]
+fn test_estimate_mock_server() {
+    let ahead = chrono::Utc::now() + chrono::TimeDelta::seconds(5);
+    let ntp = crate::mock_ntp::MockNtpServer::start(ahead);
+
+    let (_, estimate) = estimate(ntp.address(), NtpVersion::default()).unwrap();
+    assert!((estimate.offset - 5.0).abs() < 0.1, "{estimate:?}");
+    assert!((0.0..0.1).contains(&estimate.delay.abs()), "{estimate:?}");
+}
+
+#[test]
+fn test_estimate_ipv6() {
+    let ntp = crate::mock_ntp::MockNtpServer::start_on("[::1]:0", chrono::Utc::now());
+
+    let (_, estimate) = estimate(ntp.address(), NtpVersion::default()).unwrap();
+    assert!(estimate.offset.abs() < 0.1, "{estimate:?}");
+    assert_eq!(ntp.requests(), 1);
+}
+
+#[test]
+fn test_exchange_rejects_mismatch() {
+    let ntp = crate::mock_ntp::MockNtpServer::start(chrono::Utc::now());
+    assert!(request(ntp.address(), NtpVersion::default()).is_ok());
+
+    ntp.set_echo_origin(false);
+    let err = request(ntp.address(), NtpVersion::default()).unwrap_err();
+    assert!(err.to_string().contains("origin timestamp"), "{err}");
+
+    ntp.set_echo_origin(true);
+    ntp.set_mode(3);
+    let err = request(ntp.address(), NtpVersion::default()).unwrap_err();
+    assert!(err.to_string().contains("Client mode"), "{err}");
+}
+
+#[test]
+fn test_parse_server() {
+    assert_eq!(
+        parse_server("pool.ntp.org"),
+        Some("pool.ntp.org:123".to_owned())
+    );
+    assert_eq!(
+        parse_server("127.0.0.1:4123"),
+        Some("127.0.0.1:4123".to_owned())
+    );
+    assert_eq!(parse_server("[::1]:123"), Some("[::1]:123".to_owned()));
+    assert_eq!(parse_server("[::1]"), Some("[::1]:123".to_owned()));
+    for invalid in [
+        "",
+        ":123",
+        "pool.ntp.org:0",
+        "pool.ntp.org:http",
+        "-a.org",
+        "a..org",
+        "a b",
+        "[nope]:123",
+        "::1",
+    ] {
+        assert_eq!(parse_server(invalid), None, "{invalid}");
+    }
+}
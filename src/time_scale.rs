@@ -0,0 +1,134 @@
+//! Atomic time scales derived from UTC with a bundled leap second table.
+//!
+//! The table must be updated, and the service released, whenever the IERS
+//! announces a new leap second (Bulletin C), otherwise TAI and GPS drift by a
+//! second from its effective date.
+
+use std::str::FromStr;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde_derive::{Deserialize, Serialize};
+
+/// Unix time each TAI − UTC offset became effective, from the introduction of
+/// whole leap seconds in 1972 up to the one of 2017-01-01
+const LEAP_SECONDS: [(i64, i64); 28] = [
+    (63_072_000, 10),    // 1972-01-01
+    (78_796_800, 11),    // 1972-07-01
+    (94_694_400, 12),    // 1973-01-01
+    (126_230_400, 13),   // 1974-01-01
+    (157_766_400, 14),   // 1975-01-01
+    (189_302_400, 15),   // 1976-01-01
+    (220_924_800, 16),   // 1977-01-01
+    (252_460_800, 17),   // 1978-01-01
+    (283_996_800, 18),   // 1979-01-01
+    (315_532_800, 19),   // 1980-01-01
+    (362_793_600, 20),   // 1981-07-01
+    (394_329_600, 21),   // 1982-07-01
+    (425_865_600, 22),   // 1983-07-01
+    (489_024_000, 23),   // 1985-07-01
+    (567_993_600, 24),   // 1988-01-01
+    (631_152_000, 25),   // 1990-01-01
+    (662_688_000, 26),   // 1991-01-01
+    (709_948_800, 27),   // 1992-07-01
+    (741_484_800, 28),   // 1993-07-01
+    (773_020_800, 29),   // 1994-07-01
+    (820_454_400, 30),   // 1996-01-01
+    (867_715_200, 31),   // 1997-07-01
+    (915_148_800, 32),   // 1999-01-01
+    (1_136_073_600, 33), // 2006-01-01
+    (1_230_768_000, 34), // 2009-01-01
+    (1_341_100_800, 35), // 2012-07-01
+    (1_435_708_800, 36), // 2015-07-01
+    (1_483_228_800, 37), // 2017-01-01
+];
+
+/// GPS time is a constant 19 seconds behind TAI
+const GPS_TAI_OFFSET: i64 = 19;
+
+/// Deserialized through `FromStr`, so `?scale=` is case-insensitive too
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase", try_from = "String")]
+pub enum TimeScale {
+    #[default]
+    Utc,
+    Tai,
+    Gps,
+}
+
+impl FromStr for TimeScale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "utc" => Ok(Self::Utc),
+            "tai" => Ok(Self::Tai),
+            "gps" => Ok(Self::Gps),
+            _ => Err(format!("Unknown time scale {s}")),
+        }
+    }
+}
+
+impl TryFrom<String> for TimeScale {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// TAI − UTC in seconds at the instant, the 1972 offset is used before that
+pub fn tai_offset(time: DateTime<Utc>) -> i64 {
+    let timestamp = time.timestamp();
+    LEAP_SECONDS
+        .iter()
+        .rev()
+        .find(|(since, _)| *since <= timestamp)
+        .map_or(LEAP_SECONDS[0].1, |(_, offset)| *offset)
+}
+
+impl TimeScale {
+    /// Designator used as the `tz` of a time in this scale
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Utc => "UTC",
+            Self::Tai => "TAI",
+            Self::Gps => "GPS",
+        }
+    }
+
+    /// The UTC instant read on this scale's clock
+    pub fn from_utc(self, time: DateTime<Utc>) -> DateTime<Utc> {
+        let offset = match self {
+            Self::Utc => 0,
+            Self::Tai => tai_offset(time),
+            Self::Gps => tai_offset(time) - GPS_TAI_OFFSET,
+        };
+        time + TimeDelta::seconds(offset)
+    }
+}
+
+#[test]
+fn test_time_scales() {
+    use chrono::TimeZone;
+
+    let time = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+    assert_eq!(TimeScale::Utc.from_utc(time), time);
+    assert_eq!(TimeScale::Tai.from_utc(time), time + TimeDelta::seconds(37));
+    assert_eq!(TimeScale::Gps.from_utc(time), time + TimeDelta::seconds(18));
+
+    // the day before and the day of the 2017 leap second
+    let day_before = Utc.with_ymd_and_hms(2016, 12, 31, 12, 0, 0).unwrap();
+    assert_eq!(tai_offset(day_before), 36);
+    assert_eq!(
+        tai_offset(Utc.with_ymd_and_hms(2017, 1, 1, 0, 0, 0).unwrap()),
+        37
+    );
+    // GPS epoch, TAI - UTC was 19s so GPS and UTC matched
+    let gps_epoch = Utc.with_ymd_and_hms(1980, 1, 6, 0, 0, 0).unwrap();
+    assert_eq!(TimeScale::Gps.from_utc(gps_epoch), gps_epoch);
+
+    assert_eq!("GPS".parse(), Ok(TimeScale::Gps));
+    assert!("tt".parse::<TimeScale>().is_err());
+    assert_eq!(serde_json::from_str(r#""Tai""#).ok(), Some(TimeScale::Tai));
+    assert!(serde_json::from_str::<TimeScale>(r#""tt""#).is_err());
+}
@@ -0,0 +1,655 @@
+use chrono::{
+    DateTime, Datelike, FixedOffset, LocalResult, Months, NaiveDate, NaiveDateTime, NaiveTime,
+    Offset, TimeDelta, TimeZone, Timelike, Utc,
+};
+use chrono_tz::{OffsetComponents, OffsetName, Tz, TzOffset, TZ_VARIANTS};
+
+use std::{collections::HashMap, fs, path::Path, sync::OnceLock};
+
+use crate::models::{
+    LocalInterpretation, LocalStatus, LocalValidation, Period, Remaining, ServerTimezone,
+    Transition, ZoneAlias, ZoneOffset,
+};
+
+/// `backward` file of the tzdata chrono-tz is built from, the `Link TARGET
+/// LINK-NAME` lines of renamed and merged zones
+const BACKWARD: &str = include_str!("../data/backward");
+
+/// Host zone files in the order they are read when `TZ` is not set
+const LOCALTIME: &str = "/etc/localtime";
+const TIMEZONE_FILE: &str = "/etc/timezone";
+
+/// Step used while scanning for offset changes. A step only sees a change when
+/// the offsets at its ends differ, so it must stay under the shortest time an
+/// offset changes and back in the tz database: the 7 hours of Pacific/Easter on
+/// 1982-03-14.
+const SCAN_STEP_HOURS: i64 = 6;
+
+fn offset_at(tz: &Tz, time: &DateTime<Utc>) -> TzOffset {
+    tz.offset_from_utc_datetime(&time.naive_utc())
+}
+
+/// True when both offsets share their UTC offset and abbreviation
+fn same_offset(a: &TzOffset, b: &TzOffset) -> bool {
+    a.fix() == b.fix() && a.abbreviation() == b.abbreviation()
+}
+
+/// Binary search the first whole second in (from, to] where the offset differs from `from`
+fn find_transition(tz: &Tz, from: DateTime<Utc>, to: DateTime<Utc>) -> DateTime<Utc> {
+    let before = offset_at(tz, &from);
+    let (mut low, mut high) = (from.timestamp(), to.timestamp());
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        if Utc
+            .timestamp_opt(mid, 0)
+            .single()
+            .is_some_and(|mid| same_offset(&offset_at(tz, &mid), &before))
+        {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Utc.timestamp_opt(high, 0).single().unwrap_or(to)
+}
+
+/// Offset in effect for the zone at the given instant, past instants use the
+/// historical rules of the zone
+pub fn zone_offset(tz: &Tz, at: DateTime<Utc>) -> ZoneOffset {
+    let offset = tz.offset_from_utc_datetime(&at.naive_utc());
+
+    ZoneOffset {
+        zone: tz.name().to_owned(),
+        at: at.to_rfc3339(),
+        offset: offset.fix().to_string(),
+        offset_seconds: offset.fix().local_minus_utc(),
+        abbreviation: offset.abbreviation().unwrap_or_default().to_owned(),
+        is_dst: !offset.dst_offset().is_zero(),
+        alias: alias(tz),
+    }
+}
+
+/// Instant of a local wall clock time in the zone, a repeated time resolves to
+/// its first occurrence and a skipped one is read with the offset before the gap
+pub fn resolve_local(
+    tz: &Tz,
+    local: NaiveDateTime,
+) -> Option<(DateTime<Utc>, LocalInterpretation)> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(time) => Some((time.with_timezone(&Utc), LocalInterpretation::Exact)),
+        LocalResult::Ambiguous(earliest, _) => {
+            Some((earliest.with_timezone(&Utc), LocalInterpretation::Earliest))
+        }
+        LocalResult::None => {
+            let before = tz
+                .from_local_datetime(&(local - TimeDelta::days(1)))
+                .earliest()?
+                .offset()
+                .fix();
+            let time = (local - TimeDelta::seconds(i64::from(before.local_minus_utc()))).and_utc();
+            Some((time, LocalInterpretation::Shifted))
+        }
+    }
+}
+
+/// Next `count` fires of `schedule` after `after`, matched against the wall
+/// clock of `tz` then resolved like `resolve_local`: a repeated time fires
+/// once on its first occurrence, a skipped one moves forward by the gap
+pub fn next_fires(
+    schedule: &cron::Schedule,
+    tz: &Tz,
+    after: DateTime<Utc>,
+    count: usize,
+) -> Vec<(DateTime<Utc>, LocalInterpretation)> {
+    // the naive wall clock as `Utc` has no gap nor overlap to skip or repeat
+    let wall = after.with_timezone(tz).naive_local().and_utc();
+    let mut last = after;
+    schedule
+        .after(&wall)
+        .filter_map(|local| resolve_local(tz, local.naive_utc()))
+        .filter(|(fire, _)| {
+            // a shifted fire can land on the next one, an earliest one before `after`
+            let later = *fire > last;
+            if later {
+                last = *fire;
+            }
+            later
+        })
+        .take(count)
+        .collect()
+}
+
+/// Whole seconds from `local` to the next minute, hour and midnight of its
+/// wall clock. The midnight is resolved in `tz` when given, so days with a
+/// DST change are 23 or 25 hours long
+pub fn remaining(local: DateTime<FixedOffset>, tz: Option<&Tz>) -> Remaining {
+    let since_midnight = i64::from(local.num_seconds_from_midnight());
+    let fixed_day = 86_400 - since_midnight;
+    let seconds_to_next_day = tz
+        .zip(local.date_naive().succ_opt())
+        .and_then(|(tz, tomorrow)| resolve_local(tz, tomorrow.and_time(NaiveTime::MIN)))
+        .map_or(fixed_day, |(midnight, _)| {
+            midnight.timestamp() - local.timestamp()
+        });
+    Remaining {
+        seconds_to_next_minute: 60 - since_midnight % 60,
+        seconds_to_next_hour: 3_600 - since_midnight % 3_600,
+        seconds_to_next_day,
+    }
+}
+
+/// Instants a local wall clock time maps to, none in a gap and two in an overlap
+pub fn validate_local(tz: &Tz, local: NaiveDateTime) -> LocalValidation {
+    let (status, candidates) = match tz.from_local_datetime(&local) {
+        LocalResult::Single(time) => (LocalStatus::Unique, vec![time]),
+        LocalResult::Ambiguous(earliest, latest) => {
+            (LocalStatus::Ambiguous, vec![earliest, latest])
+        }
+        LocalResult::None => (LocalStatus::Nonexistent, vec![]),
+    };
+    LocalValidation {
+        status,
+        candidates: candidates.iter().map(DateTime::to_rfc3339).collect(),
+    }
+}
+
+/// Canonical zone of a deprecated alias such as `Asia/Calcutta`, `None` for
+/// current zone names
+pub fn alias(tz: &Tz) -> Option<ZoneAlias> {
+    static LINKS: OnceLock<HashMap<&str, &str>> = OnceLock::new();
+    let links = LINKS.get_or_init(|| {
+        BACKWARD
+            .lines()
+            .filter_map(
+                |line| match line.split_whitespace().collect::<Vec<_>>()[..] {
+                    ["Link", target, name, ..] => Some((name, target)),
+                    _ => None,
+                },
+            )
+            .collect()
+    });
+    links.get(tz.name()).map(|canonical| ZoneAlias {
+        deprecated_alias: tz.name().to_owned(),
+        canonical: (*canonical).to_owned(),
+    })
+}
+
+#[test]
+fn test_alias() {
+    assert_eq!(
+        alias(&chrono_tz::Asia::Calcutta),
+        Some(ZoneAlias {
+            deprecated_alias: "Asia/Calcutta".to_owned(),
+            canonical: "Asia/Kolkata".to_owned(),
+        })
+    );
+    assert_eq!(
+        alias(&chrono_tz::US::Pacific).map(|alias| alias.canonical),
+        Some("America/Los_Angeles".to_owned())
+    );
+    assert_eq!(alias(&chrono_tz::Asia::Kolkata), None);
+    assert_eq!(alias(&chrono_tz::Europe::Berlin), None);
+}
+
+/// Monday to Sunday of the ISO week containing `date`
+pub fn iso_week(date: NaiveDate) -> Vec<NaiveDate> {
+    let monday = date - TimeDelta::days(date.weekday().num_days_from_monday().into());
+    monday.iter_days().take(7).collect()
+}
+
+/// Instants of the local midnights starting the period containing `date` and
+/// the next one, so a day crossing a DST change lasts 23 or 25 hours
+pub fn period_bounds(
+    tz: &Tz,
+    date: NaiveDate,
+    period: Period,
+) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let (start, end) = match period {
+        Period::Day => (date, date.succ_opt()?),
+        Period::Month => {
+            let start = date.with_day(1)?;
+            (start, start.checked_add_months(Months::new(1))?)
+        }
+        Period::Year => {
+            let start = date.with_ordinal(1)?;
+            (start, start.with_year(start.year() + 1)?)
+        }
+    };
+    let midnight = |date: NaiveDate| resolve_local(tz, date.and_time(Default::default()));
+    Some((midnight(start)?.0, midnight(end)?.0))
+}
+
+/// First instant in (start, end] where the offset of the zone changes
+pub fn next_transition(tz: &Tz, start: DateTime<Utc>, end: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let step = TimeDelta::hours(SCAN_STEP_HOURS);
+    let mut current = start;
+
+    while current < end {
+        let next = (current + step).min(end);
+        if !same_offset(&offset_at(tz, &current), &offset_at(tz, &next)) {
+            return Some(find_transition(tz, current, next));
+        }
+        current = next;
+    }
+
+    None
+}
+
+pub fn transitions(tz: &Tz, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<Transition> {
+    upcoming_transitions(tz, start, end)
+        .into_iter()
+        .map(|(_, transition)| transition)
+        .collect()
+}
+
+/// Transitions in (start, end] along with their instant
+pub fn upcoming_transitions(
+    tz: &Tz,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Vec<(DateTime<Utc>, Transition)> {
+    let mut ret = vec![];
+    let mut current = start;
+
+    while let Some(at) = next_transition(tz, current, end) {
+        let from = tz.offset_from_utc_datetime(&(at - TimeDelta::seconds(1)).naive_utc());
+        let to = tz.offset_from_utc_datetime(&at.naive_utc());
+
+        ret.push((
+            at,
+            Transition {
+                at: at.to_rfc3339(),
+                from_offset: from.fix().to_string(),
+                to_offset: to.fix().to_string(),
+                abbreviation: to.abbreviation().unwrap_or_default().to_owned(),
+            },
+        ));
+        current = at;
+    }
+
+    ret
+}
+
+/// Zone the host is configured in, the `TZ` value (without the leading `:`)
+/// or else the system one read from the `/etc/localtime` link or
+/// `/etc/timezone`. `UTC` with a note when neither tells
+pub fn server_timezone(tz_env: Option<&str>) -> ServerTimezone {
+    let known = |timezone: String, source| ServerTimezone {
+        timezone,
+        source,
+        note: None,
+    };
+    if let Some(tz) = tz_env
+        .map(|tz| tz.trim_start_matches(':'))
+        .filter(|tz| !tz.is_empty())
+    {
+        return known(tz.to_owned(), "TZ");
+    }
+
+    let linked = fs::read_link(LOCALTIME).ok().and_then(|target| {
+        let target = target.to_string_lossy().into_owned();
+        target
+            .split_once("zoneinfo/")
+            .map(|(_, name)| name.to_owned())
+    });
+    let written = || {
+        fs::read_to_string(Path::new(TIMEZONE_FILE))
+            .ok()
+            .map(|name| name.trim().to_owned())
+            .filter(|name| !name.is_empty())
+    };
+    match linked.or_else(written) {
+        Some(name) => known(name, "system"),
+        None => ServerTimezone {
+            timezone: "UTC".to_owned(),
+            source: "unknown",
+            note: Some("Host timezone unknown, neither TZ nor the system zone is set".to_owned()),
+        },
+    }
+}
+
+/// Sorted distinct first segments of the `continent/region` zone names,
+/// zones without a region such as `UTC` are left out
+pub fn continents() -> Vec<&'static str> {
+    let mut continents: Vec<_> = TZ_VARIANTS
+        .iter()
+        .filter_map(|tz| tz.name().split_once('/'))
+        .map(|(continent, _)| continent)
+        .collect();
+    continents.sort_unstable();
+    continents.dedup();
+    continents
+}
+
+/// Sorted region names under the continent, the full remainder for deeper
+/// zones like `Argentina/Buenos_Aires`, `None` for an unknown continent
+pub fn regions(continent: &str) -> Option<Vec<&'static str>> {
+    let mut regions: Vec<_> = TZ_VARIANTS
+        .iter()
+        .filter_map(|tz| tz.name().split_once('/'))
+        .filter(|(name, _)| name.eq_ignore_ascii_case(continent))
+        .map(|(_, region)| region)
+        .collect();
+    if regions.is_empty() {
+        return None;
+    }
+    regions.sort_unstable();
+    Some(regions)
+}
+
+/// Continents close to a misspelled one, by edit distance ignoring case
+pub fn suggest_continents(continent: &str) -> Vec<&'static str> {
+    const MAX_DISTANCE: usize = 2;
+    let continent = continent.to_ascii_lowercase();
+    continents()
+        .into_iter()
+        .filter(|candidate| {
+            edit_distance(&candidate.to_ascii_lowercase(), &continent) <= MAX_DISTANCE
+        })
+        .collect()
+}
+
+/// Levenshtein distance over bytes, zone names are ASCII
+fn edit_distance(a: &str, b: &str) -> usize {
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.bytes().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.bytes().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[test]
+fn test_regions() {
+    let europe = regions("Europe").unwrap();
+    for region in ["Berlin", "London", "Paris"] {
+        assert!(europe.contains(&region), "{region}");
+    }
+    assert!(europe.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert_eq!(regions("europe"), Some(europe));
+
+    assert!(regions("America")
+        .unwrap()
+        .contains(&"Argentina/Buenos_Aires"));
+    assert_eq!(regions("Atlantis"), None);
+    assert_eq!(suggest_continents("Eurpoe"), vec!["Europe"]);
+}
+
+#[test]
+fn test_continents() {
+    let continents = continents();
+    for continent in ["Africa", "America", "Asia", "Europe", "Pacific"] {
+        assert!(continents.contains(&continent), "{continent}");
+    }
+    assert!(!continents.contains(&"UTC"));
+    assert!(continents.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn test_transitions_europe_london() {
+    let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    let end = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+    let ret = transitions(&chrono_tz::Europe::London, start, end);
+
+    assert_eq!(ret.len(), 2);
+    assert_eq!(ret[0].at, "2024-03-31T01:00:00+00:00");
+    assert_eq!(ret[0].from_offset, "+00:00");
+    assert_eq!(ret[0].to_offset, "+01:00");
+    assert_eq!(ret[0].abbreviation, "BST");
+    assert_eq!(ret[1].at, "2024-10-27T01:00:00+00:00");
+    assert_eq!(ret[1].abbreviation, "GMT");
+}
+
+#[test]
+fn test_transitions_no_dst() {
+    let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    let end = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+    assert!(transitions(&chrono_tz::Asia::Tokyo, start, end).is_empty());
+}
+
+#[test]
+fn test_transitions_close_pair() {
+    let start = Utc.with_ymd_and_hms(1982, 3, 13, 0, 0, 0).unwrap();
+    let end = Utc.with_ymd_and_hms(1982, 3, 16, 0, 0, 0).unwrap();
+    let ret = transitions(&chrono_tz::Pacific::Easter, start, end);
+
+    let at: Vec<_> = ret.iter().map(|t| (&*t.at, &*t.to_offset)).collect();
+    assert_eq!(
+        at,
+        [
+            ("1982-03-14T03:00:00+00:00", "-07:00"),
+            ("1982-03-14T10:00:00+00:00", "-06:00")
+        ]
+    );
+}
+
+#[test]
+fn test_zone_offset_historical() {
+    // West Germany only adopted summer time again in 1980
+    let ret = zone_offset(
+        &chrono_tz::Europe::Berlin,
+        Utc.with_ymd_and_hms(1975, 6, 1, 0, 0, 0).unwrap(),
+    );
+    assert_eq!(ret.offset, "+01:00");
+    assert_eq!(ret.offset_seconds, 3600);
+    assert_eq!(ret.abbreviation, "CET");
+    assert!(!ret.is_dst);
+
+    let ret = zone_offset(
+        &chrono_tz::Europe::Berlin,
+        Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap(),
+    );
+    assert_eq!(ret.offset, "+02:00");
+    assert_eq!(ret.abbreviation, "CEST");
+    assert!(ret.is_dst);
+}
+
+#[test]
+fn test_validate_local() {
+    let berlin = chrono_tz::Europe::Berlin;
+    let local = |stri| NaiveDateTime::parse_from_str(stri, "%Y-%m-%dT%H:%M").unwrap();
+
+    assert_eq!(
+        validate_local(&berlin, local("2024-07-01T12:00")),
+        LocalValidation {
+            status: LocalStatus::Unique,
+            candidates: vec!["2024-07-01T12:00:00+02:00".to_owned()],
+        }
+    );
+    // spring forward gap
+    assert_eq!(
+        validate_local(&berlin, local("2024-03-31T02:30")),
+        LocalValidation {
+            status: LocalStatus::Nonexistent,
+            candidates: vec![],
+        }
+    );
+    // fall back overlap
+    assert_eq!(
+        validate_local(&berlin, local("2024-10-27T02:30")),
+        LocalValidation {
+            status: LocalStatus::Ambiguous,
+            candidates: vec![
+                "2024-10-27T02:30:00+02:00".to_owned(),
+                "2024-10-27T02:30:00+01:00".to_owned(),
+            ],
+        }
+    );
+}
+
+#[test]
+fn test_resolve_local() {
+    let berlin = chrono_tz::Europe::Berlin;
+    let local = |stri| NaiveDateTime::parse_from_str(stri, "%Y-%m-%dT%H:%M").unwrap();
+
+    assert_eq!(
+        resolve_local(&berlin, local("2024-07-01T12:00")),
+        Some((
+            Utc.with_ymd_and_hms(2024, 7, 1, 10, 0, 0).unwrap(),
+            LocalInterpretation::Exact
+        ))
+    );
+    // 02:30 doesn't exist on 2024-03-31, it reads as 03:30 CEST
+    assert_eq!(
+        resolve_local(&berlin, local("2024-03-31T02:30")),
+        Some((
+            Utc.with_ymd_and_hms(2024, 3, 31, 1, 30, 0).unwrap(),
+            LocalInterpretation::Shifted
+        ))
+    );
+    // 02:30 happens twice on 2024-10-27, first in CEST
+    assert_eq!(
+        resolve_local(&berlin, local("2024-10-27T02:30")),
+        Some((
+            Utc.with_ymd_and_hms(2024, 10, 27, 0, 30, 0).unwrap(),
+            LocalInterpretation::Earliest
+        ))
+    );
+}
+
+#[test]
+fn test_period_bounds() {
+    use chrono_tz::{America::Sao_Paulo, Europe::London};
+
+    let bounds = |tz, date: &str, period| {
+        let (start, end) = period_bounds(tz, date.parse().unwrap(), period).unwrap();
+        (start.to_rfc3339(), end.to_rfc3339())
+    };
+
+    // clocks go forward at 01:00 GMT, the day is 23 hours long
+    let (start, end) = bounds(&London, "2026-03-29", Period::Day);
+    assert_eq!(start, "2026-03-29T00:00:00+00:00");
+    assert_eq!(end, "2026-03-29T23:00:00+00:00");
+
+    // and back at 02:00 BST, 25 hours
+    let (start, end) = bounds(&London, "2026-10-25", Period::Day);
+    assert_eq!(start, "2026-10-24T23:00:00+00:00");
+    assert_eq!(end, "2026-10-26T00:00:00+00:00");
+
+    // 1999 DST in Sao Paulo started at midnight, the day began at 01:00
+    let (start, _) = bounds(&Sao_Paulo, "1999-10-03", Period::Day);
+    assert_eq!(start, "1999-10-03T03:00:00+00:00");
+
+    assert_eq!(
+        bounds(&London, "2026-07-14", Period::Month),
+        (
+            "2026-06-30T23:00:00+00:00".to_owned(),
+            "2026-07-31T23:00:00+00:00".to_owned()
+        )
+    );
+    assert_eq!(
+        bounds(&London, "2026-07-14", Period::Year),
+        (
+            "2026-01-01T00:00:00+00:00".to_owned(),
+            "2027-01-01T00:00:00+00:00".to_owned()
+        )
+    );
+}
+
+#[test]
+fn test_server_timezone() {
+    let tz = server_timezone(Some(":Europe/Paris"));
+    assert_eq!((tz.timezone.as_str(), tz.source), ("Europe/Paris", "TZ"));
+    assert!(tz.note.is_none());
+
+    // an empty TZ is unset, the answer then depends on the host
+    let tz = server_timezone(Some(""));
+    assert_ne!(tz.source, "TZ");
+    assert_eq!(tz.note.is_some(), tz.source == "unknown");
+}
+
+#[test]
+fn test_remaining() {
+    let until = |tz: &Tz, utc: DateTime<Utc>| {
+        let local = tz.from_utc_datetime(&utc.naive_utc()).fixed_offset();
+        let ret = remaining(local, Some(tz));
+        (
+            ret.seconds_to_next_minute,
+            ret.seconds_to_next_hour,
+            ret.seconds_to_next_day,
+        )
+    };
+
+    let utc = Utc.with_ymd_and_hms(2026, 7, 1, 12, 59, 58).unwrap();
+    assert_eq!(until(&Tz::UTC, utc), (2, 2, 11 * 3600 + 2));
+    assert_eq!(
+        until(&Tz::UTC, utc + TimeDelta::milliseconds(2500)),
+        (60, 3600, 11 * 3600)
+    );
+    // a half hour zone reaches the hour with the wall clock
+    assert_eq!(until(&Tz::Asia__Kolkata, utc), (2, 1802, 5 * 3600 + 1802));
+
+    // New York springs forward on 2026-03-08 and falls back on 2026-11-01
+    let new_york = Tz::America__New_York;
+    let spring = Utc.with_ymd_and_hms(2026, 3, 8, 5, 0, 0).unwrap();
+    assert_eq!(until(&new_york, spring).2, 23 * 3600);
+    let fall = Utc.with_ymd_and_hms(2026, 11, 1, 4, 0, 0).unwrap();
+    assert_eq!(until(&new_york, fall).2, 25 * 3600);
+
+    // without the zone the offset is assumed constant
+    let local = new_york
+        .from_utc_datetime(&spring.naive_utc())
+        .fixed_offset();
+    assert_eq!(remaining(local, None).seconds_to_next_day, 24 * 3600);
+}
+
+#[test]
+fn test_next_fires() {
+    use LocalInterpretation::*;
+
+    let new_york = Tz::America__New_York;
+    let fires = |cron, after, count| {
+        let schedule = crate::utils::parse_cron(cron).unwrap();
+        next_fires(&schedule, &new_york, after, count)
+            .into_iter()
+            .map(|(fire, interpretation)| (fire.to_rfc3339(), interpretation))
+            .collect::<Vec<_>>()
+    };
+    let expected = |fires: &[(&str, LocalInterpretation)]| {
+        fires
+            .iter()
+            .map(|(fire, interpretation)| (fire.to_string(), *interpretation))
+            .collect::<Vec<_>>()
+    };
+
+    // 02:30 is skipped on 2026-03-08 and fires at 03:30 EDT instead
+    let spring = Utc.with_ymd_and_hms(2026, 3, 7, 12, 0, 0).unwrap();
+    assert_eq!(
+        fires("30 2 * * *", spring, 3),
+        expected(&[
+            ("2026-03-08T07:30:00+00:00", Shifted),
+            ("2026-03-09T06:30:00+00:00", Exact),
+            ("2026-03-10T06:30:00+00:00", Exact),
+        ])
+    );
+    // a shifted 02:00 lands on the 03:00 fire, which fires once
+    assert_eq!(
+        fires("0 * * * *", spring + TimeDelta::minutes(17 * 60 + 30), 3),
+        expected(&[
+            ("2026-03-08T06:00:00+00:00", Exact),
+            ("2026-03-08T07:00:00+00:00", Shifted),
+            ("2026-03-08T08:00:00+00:00", Exact),
+        ])
+    );
+
+    // 01:00 to 01:59 repeats on 2026-11-01, only the EDT pass fires
+    let fall = Utc.with_ymd_and_hms(2026, 11, 1, 4, 50, 0).unwrap();
+    assert_eq!(
+        fires("*/30 1 * * *", fall, 3),
+        expected(&[
+            ("2026-11-01T05:00:00+00:00", Earliest),
+            ("2026-11-01T05:30:00+00:00", Earliest),
+            ("2026-11-02T06:00:00+00:00", Exact),
+        ])
+    );
+    // during the EST pass the repeated times already fired
+    assert_eq!(
+        fires("*/30 1 * * *", fall + TimeDelta::minutes(80), 1),
+        expected(&[("2026-11-02T06:00:00+00:00", Exact)])
+    );
+}
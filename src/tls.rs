@@ -0,0 +1,162 @@
+use std::{fs::File, io, io::BufReader, str::FromStr, sync::Arc};
+
+use rustls::{
+    crypto::ring,
+    pki_types::{CertificateDer, PrivateKeyDer},
+    version, ServerConfig, SupportedProtocolVersion,
+};
+use serde_derive::Serialize;
+use thiserror::Error;
+
+static TLS13_ONLY: &[&SupportedProtocolVersion] = &[&version::TLS13];
+
+/// Lowest TLS protocol version accepted by the server, anything older than 1.2
+/// is deprecated (RFC 8996) and rejected at startup. Only AEAD cipher suites
+/// are offered whatever the version.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub enum TlsVersion {
+    #[default]
+    #[serde(rename = "1.2")]
+    Tls12,
+    #[serde(rename = "1.3")]
+    Tls13,
+}
+
+impl FromStr for TlsVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().trim_start_matches("tls") {
+            "1.2" => Ok(Self::Tls12),
+            "1.3" => Ok(Self::Tls13),
+            "1.0" | "1.1" => Err(format!("TLS {s} is deprecated, use 1.2 or 1.3")),
+            _ => Err(format!("Unknown TLS version {s}, use 1.2 or 1.3")),
+        }
+    }
+}
+
+impl TlsVersion {
+    fn protocol_versions(self) -> &'static [&'static SupportedProtocolVersion] {
+        match self {
+            Self::Tls12 => rustls::ALL_VERSIONS,
+            Self::Tls13 => TLS13_ONLY,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum TlsError {
+    #[error("failed to read {path} : {err}")]
+    Io { path: String, err: io::Error },
+    #[error("no private key found in {0}")]
+    MissingKey(String),
+    #[error(transparent)]
+    Rustls(#[from] rustls::Error),
+}
+
+fn open(path: &str) -> Result<BufReader<File>, TlsError> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|err| TlsError::Io {
+            path: path.to_owned(),
+            err,
+        })
+}
+
+/// Load the PEM certificate chain and private key and build the server config
+pub fn load_server_config(
+    cert_path: &str,
+    key_path: &str,
+    min_version: TlsVersion,
+) -> Result<ServerConfig, TlsError> {
+    let certs = rustls_pemfile::certs(&mut open(cert_path)?)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| TlsError::Io {
+            path: cert_path.to_owned(),
+            err,
+        })?;
+
+    let key = rustls_pemfile::private_key(&mut open(key_path)?)
+        .map_err(|err| TlsError::Io {
+            path: key_path.to_owned(),
+            err,
+        })?
+        .ok_or_else(|| TlsError::MissingKey(key_path.to_owned()))?;
+
+    server_config(certs, key, min_version)
+}
+
+pub fn server_config(
+    certs: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+    min_version: TlsVersion,
+) -> Result<ServerConfig, TlsError> {
+    let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_protocol_versions(min_version.protocol_versions())?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+    Ok(config)
+}
+
+#[test]
+fn test_tls_version_from_str() {
+    assert_eq!("1.2".parse(), Ok(TlsVersion::Tls12));
+    assert_eq!("TLS1.3".parse(), Ok(TlsVersion::Tls13));
+    assert!("1.0".parse::<TlsVersion>().is_err());
+    assert!("1.1".parse::<TlsVersion>().is_err());
+    assert!("ssl3".parse::<TlsVersion>().is_err());
+}
+
+#[cfg(test)]
+fn handshake(
+    server: ServerConfig,
+    cert: CertificateDer<'static>,
+    client_version: &'static SupportedProtocolVersion,
+) -> bool {
+    use rustls::{ClientConfig, ClientConnection, RootCertStore, ServerConnection};
+
+    let mut roots = RootCertStore::empty();
+    roots.add(cert).unwrap();
+    let client = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_protocol_versions(&[client_version])
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    let mut client =
+        ClientConnection::new(Arc::new(client), "localhost".try_into().unwrap()).unwrap();
+    let mut server = ServerConnection::new(Arc::new(server)).unwrap();
+
+    while client.is_handshaking() || server.is_handshaking() {
+        let mut buf = vec![];
+        client.write_tls(&mut buf).unwrap();
+        server.read_tls(&mut buf.as_slice()).unwrap();
+        if server.process_new_packets().is_err() {
+            return false;
+        }
+
+        let mut buf = vec![];
+        server.write_tls(&mut buf).unwrap();
+        client.read_tls(&mut buf.as_slice()).unwrap();
+        if client.process_new_packets().is_err() {
+            return false;
+        }
+    }
+
+    true
+}
+
+#[test]
+fn test_server_config_min_version() {
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()]).unwrap();
+    let cert = certified.cert.der().clone();
+    let key = || PrivateKeyDer::try_from(certified.signing_key.serialize_der()).unwrap();
+
+    let tls12 = server_config(vec![cert.clone()], key(), TlsVersion::Tls12).unwrap();
+    assert!(handshake(tls12.clone(), cert.clone(), &version::TLS12));
+    assert!(handshake(tls12, cert.clone(), &version::TLS13));
+
+    let tls13 = server_config(vec![cert.clone()], key(), TlsVersion::Tls13).unwrap();
+    assert!(!handshake(tls13.clone(), cert.clone(), &version::TLS12));
+    assert!(handshake(tls13, cert, &version::TLS13));
+}
@@ -0,0 +1,115 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, FixedOffset, Utc};
+use chrono_tz::{Tz, IANA_TZDB_VERSION, TZ_VARIANTS};
+use log::{info, warn};
+
+/// IANA zone data used for the served `/now` conversions, the database compiled
+/// into chrono-tz unless `TZDATA_DIR` points at a zoneinfo directory
+#[derive(Default)]
+pub struct TzDatabase {
+    dir: Option<PathBuf>,
+    version: Option<String>,
+    zones: HashMap<Tz, tzfile::ArcTz>,
+}
+
+impl TzDatabase {
+    /// Load every known zone from the TZif files of `dir`, zones missing from
+    /// the directory keep using the compiled data
+    pub fn load(dir: &Path) -> io::Result<Self> {
+        let zones: HashMap<_, _> = TZ_VARIANTS
+            .iter()
+            .filter_map(|tz| {
+                let content = fs::read(dir.join(tz.name())).ok()?;
+                tzfile::Tz::parse(tz.name(), &content)
+                    .inspect_err(|err| warn!("Invalid tzdata for {tz} : {err}"))
+                    .ok()
+                    .map(|file| (*tz, tzfile::ArcTz::new(file)))
+            })
+            .collect();
+
+        if zones.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no zone data found in {}", dir.display()),
+            ));
+        }
+
+        // tzdata.zi starts with a `# version 2024a` line
+        let version = fs::read_to_string(dir.join("tzdata.zi"))
+            .ok()
+            .and_then(|zi| {
+                zi.lines()
+                    .next()?
+                    .strip_prefix("# version ")
+                    .map(str::to_owned)
+            });
+
+        Ok(Self {
+            dir: Some(dir.to_owned()),
+            version,
+            zones,
+        })
+    }
+
+    pub fn log_active(&self) {
+        match &self.dir {
+            Some(dir) => info!(
+                "Using tzdata {version} from {dir} ({count} zones, the rest compiled {IANA_TZDB_VERSION})",
+                version = self.version.as_deref().unwrap_or("unknown version"),
+                dir = dir.display(),
+                count = self.zones.len(),
+            ),
+            None => info!("Using compiled tzdata {IANA_TZDB_VERSION}"),
+        }
+    }
+
+    pub fn localize(&self, tz: Tz, time: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self.zones.get(&tz) {
+            Some(file) => time.with_timezone(file).fixed_offset(),
+            None => time.with_timezone(&tz).fixed_offset(),
+        }
+    }
+}
+
+#[test]
+fn test_tz_database_fixture() {
+    use chrono::TimeZone;
+
+    // the fixture ships Asia/Tokyo data under the Europe/Berlin name so the
+    // override is observable
+    let db = TzDatabase::load(Path::new("tests/fixtures/tzdata")).unwrap();
+    assert_eq!(db.version.as_deref(), Some("2099z"));
+
+    let time = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+    assert_eq!(
+        db.localize(chrono_tz::Europe::Berlin, time)
+            .offset()
+            .local_minus_utc(),
+        9 * 3600
+    );
+    // zones missing from the directory use the compiled data
+    assert_eq!(
+        db.localize(chrono_tz::Europe::London, time)
+            .offset()
+            .local_minus_utc(),
+        0
+    );
+
+    assert_eq!(
+        TzDatabase::default()
+            .localize(chrono_tz::Europe::Berlin, time)
+            .offset()
+            .local_minus_utc(),
+        3600
+    );
+}
+
+#[test]
+fn test_tz_database_missing_dir() {
+    assert!(TzDatabase::load(Path::new("tests/fixtures/missing")).is_err());
+}
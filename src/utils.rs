@@ -1,4 +1,24 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::{Tz, TZ_VARIANTS};
+
+use std::{collections::HashMap, sync::OnceLock};
+
+/// Canonical spelling of a zone name component such as `DumontDUrville` or
+/// `Port-au-Prince`, looked up case insensitively among the components of the
+/// known zones. Unknown components are title cased
 pub fn to_camel_case(stri: &str) -> String {
+    static COMPONENTS: OnceLock<HashMap<String, &'static str>> = OnceLock::new();
+    let components = COMPONENTS.get_or_init(|| {
+        TZ_VARIANTS
+            .iter()
+            .flat_map(|tz| tz.name().split('/'))
+            .map(|component| (component.to_ascii_lowercase(), component))
+            .collect()
+    });
+
+    if let Some(canonical) = components.get(&stri.to_ascii_lowercase()) {
+        return (*canonical).to_owned();
+    }
     stri.char_indices()
         .map(|(i, c)| {
             if i == 0 {
@@ -9,3 +29,296 @@ pub fn to_camel_case(stri: &str) -> String {
         })
         .collect()
 }
+
+/// Parse an IANA zone name, falling back to normalizing the casing of each segment
+pub fn parse_tz(name: &str) -> Option<Tz> {
+    name.parse().ok().or_else(|| {
+        name.split('/')
+            .map(to_camel_case)
+            .collect::<Vec<_>>()
+            .join("/")
+            .parse()
+            .ok()
+    })
+}
+
+/// Parse either an epoch in seconds, an RFC 3339 / ISO 8601 timestamp or a
+/// bare date (midnight UTC)
+pub fn parse_timestamp(stri: &str) -> Option<DateTime<Utc>> {
+    if let Ok(epoch) = stri.parse::<i64>() {
+        return Utc.timestamp_opt(epoch, 0).single();
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(stri, "%Y-%m-%d") {
+        return Some(date.and_time(NaiveTime::MIN).and_utc());
+    }
+
+    DateTime::parse_from_rfc3339(stri)
+        .map(|time| time.with_timezone(&Utc))
+        .ok()
+}
+
+/// Largest UTC offset in use, Line Islands at +14:00
+const MAX_OFFSET_SECONDS: i32 = 14 * 3600;
+
+/// Signed duration in hours and minutes, `+8:00` or `-5:30`
+pub fn format_hours(seconds: i64) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let minutes = seconds.abs() / 60;
+    format!("{sign}{}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// Parse a fixed UTC offset, `Z`, `+HH:MM` or `-HH:MM`, up to ±14:00
+pub fn parse_offset(stri: &str) -> Option<FixedOffset> {
+    if stri.eq_ignore_ascii_case("z") {
+        return FixedOffset::east_opt(0);
+    }
+
+    let (sign, rest) = match stri.as_bytes().first()? {
+        b'+' => (1, &stri[1..]),
+        b'-' => (-1, &stri[1..]),
+        _ => return None,
+    };
+    let (hours, minutes) = rest.split_once(':')?;
+    let two_digits = |part: &str| part.len() == 2 && part.bytes().all(|b| b.is_ascii_digit());
+    if !two_digits(hours) || !two_digits(minutes) {
+        return None;
+    }
+    let hours: i32 = hours.parse().ok()?;
+    let minutes: i32 = minutes.parse().ok()?;
+    if minutes >= 60 {
+        return None;
+    }
+
+    let seconds = hours * 3600 + minutes * 60;
+    if seconds > MAX_OFFSET_SECONDS {
+        return None;
+    }
+    FixedOffset::east_opt(sign * seconds)
+}
+
+/// Parse a local wall clock time, `YYYY-MM-DDTHH:MM` with optional seconds
+pub fn parse_local_datetime(stri: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(stri, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(stri, "%Y-%m-%dT%H:%M"))
+        .ok()
+}
+
+/// Parse a cron expression, the standard 5 fields fire at second 0 and the
+/// 6 or 7 field form starts with the seconds (and ends with the years)
+pub fn parse_cron(stri: &str) -> Result<cron::Schedule, String> {
+    let expression = match stri.split_whitespace().count() {
+        5 => format!("0 {stri}"),
+        _ => stri.to_owned(),
+    };
+    expression
+        .parse()
+        .map_err(|err: cron::error::Error| err.to_string())
+}
+
+/// IMF-fixdate of RFC 9110, the format of the `Date` header
+pub fn http_date(time: DateTime<Utc>) -> String {
+    time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+const NUMBER_WORDS: [&str; 30] = [
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+    "twenty",
+    "twenty-one",
+    "twenty-two",
+    "twenty-three",
+    "twenty-four",
+    "twenty-five",
+    "twenty-six",
+    "twenty-seven",
+    "twenty-eight",
+    "twenty-nine",
+];
+
+/// English wall clock time, `four minutes past three in the afternoon`,
+/// past the half hour it counts down to the next hour
+pub fn time_in_words(hour: u32, minute: u32) -> String {
+    let (minutes, relation, hour) = match minute {
+        0 => (None, "", hour),
+        1..=30 => (Some(minute), "past", hour),
+        _ => (Some(60 - minute), "to", (hour + 1) % 24),
+    };
+    let clock = match hour {
+        0 => "midnight".to_owned(),
+        12 => "noon".to_owned(),
+        _ => {
+            let period = match hour {
+                1..=11 => "in the morning",
+                13..=17 => "in the afternoon",
+                _ => "in the evening",
+            };
+            let name = NUMBER_WORDS[(hour % 12) as usize];
+            if minutes.is_none() {
+                format!("{name} o'clock {period}")
+            } else {
+                format!("{name} {period}")
+            }
+        }
+    };
+    match minutes {
+        None => clock,
+        Some(15) => format!("quarter {relation} {clock}"),
+        Some(30) => format!("half {relation} {clock}"),
+        Some(1) => format!("one minute {relation} {clock}"),
+        Some(minutes) => format!(
+            "{} minutes {relation} {clock}",
+            NUMBER_WORDS[minutes as usize]
+        ),
+    }
+}
+
+#[test]
+fn test_time_in_words() {
+    for (hour, minute, words) in [
+        (15, 4, "four minutes past three in the afternoon"),
+        (15, 0, "three o'clock in the afternoon"),
+        (9, 15, "quarter past nine in the morning"),
+        (9, 30, "half past nine in the morning"),
+        (17, 45, "quarter to six in the evening"),
+        (6, 59, "one minute to seven in the morning"),
+        (20, 38, "twenty-two minutes to nine in the evening"),
+        (12, 0, "noon"),
+        (11, 50, "ten minutes to noon"),
+        (0, 0, "midnight"),
+        (23, 45, "quarter to midnight"),
+        (0, 1, "one minute past midnight"),
+    ] {
+        assert_eq!(time_in_words(hour, minute), words, "{hour}:{minute}");
+    }
+}
+
+#[test]
+fn test_parse_cron() {
+    let after = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 30).unwrap();
+    let first = |stri| parse_cron(stri).unwrap().after(&after).next().unwrap();
+    assert_eq!(
+        first("*/15 * * * *"),
+        after + chrono::TimeDelta::seconds(870)
+    );
+    assert_eq!(first("30 0 0 * * *"), after + chrono::TimeDelta::days(1));
+    assert_eq!(first("@daily"), after + chrono::TimeDelta::seconds(86_370));
+
+    for stri in ["", "* * * *", "61 * * * *", "0 0 * * 0", "every day"] {
+        assert!(parse_cron(stri).is_err(), "{stri}");
+    }
+}
+
+#[test]
+fn test_http_date() {
+    let time = Utc.with_ymd_and_hms(1994, 11, 6, 8, 49, 37).unwrap();
+    assert_eq!(http_date(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+}
+
+#[test]
+fn test_format_hours() {
+    assert_eq!(format_hours(8 * 3600), "+8:00");
+    assert_eq!(format_hours(0), "+0:00");
+    assert_eq!(format_hours(-(5 * 3600 + 30 * 60)), "-5:30");
+    assert_eq!(format_hours(13 * 3600 + 45 * 60), "+13:45");
+}
+
+#[test]
+fn test_parse_offset() {
+    assert_eq!(parse_offset("Z"), FixedOffset::east_opt(0));
+    assert_eq!(
+        parse_offset("+05:30"),
+        FixedOffset::east_opt(5 * 3600 + 1800)
+    );
+    assert_eq!(parse_offset("-03:00"), FixedOffset::west_opt(3 * 3600));
+    assert_eq!(parse_offset("+14:00"), FixedOffset::east_opt(14 * 3600));
+    assert_eq!(parse_offset("+14:30"), None);
+    assert_eq!(parse_offset("-15:00"), None);
+    assert_eq!(parse_offset("05:30"), None);
+    assert_eq!(parse_offset("+5:30"), None);
+    assert_eq!(parse_offset("+05:75"), None);
+    // the sign is only allowed once, in front
+    assert_eq!(parse_offset("+-5:00"), None);
+    assert_eq!(parse_offset("++5:30"), None);
+    assert_eq!(parse_offset("+05:-1"), None);
+}
+
+#[test]
+fn test_parse_timestamp() {
+    let expected = Utc.with_ymd_and_hms(2024, 1, 3, 10, 0, 0).unwrap();
+    assert_eq!(parse_timestamp("1704276000"), Some(expected));
+    assert_eq!(parse_timestamp("2024-01-03T10:00:00Z"), Some(expected));
+    assert_eq!(parse_timestamp("2024-01-03T12:00:00+02:00"), Some(expected));
+    assert_eq!(
+        parse_timestamp("2024-01-03"),
+        Utc.with_ymd_and_hms(2024, 1, 3, 0, 0, 0).single()
+    );
+    assert_eq!(parse_timestamp("2024-13-03"), None);
+    assert_eq!(parse_timestamp("yesterday"), None);
+}
+
+#[test]
+fn test_parse_tz() {
+    assert_eq!(parse_tz("Europe/Berlin"), Some(chrono_tz::Europe::Berlin));
+    assert_eq!(parse_tz("europe/berlin"), Some(chrono_tz::Europe::Berlin));
+    assert_eq!(
+        parse_tz("America/Argentina/Buenos_Aires"),
+        Some(chrono_tz::America::Argentina::Buenos_Aires)
+    );
+    assert_eq!(parse_tz("Europe/Atlantis"), None);
+    assert_eq!(
+        parse_tz("america/new_york"),
+        Some(chrono_tz::America::New_York)
+    );
+    assert_eq!(
+        parse_tz("antarctica/dumontdurville"),
+        Some(chrono_tz::Antarctica::DumontDUrville)
+    );
+}
+
+#[test]
+fn test_to_camel_case() {
+    for (input, canonical) in [
+        ("dumontdurville", "DumontDUrville"),
+        ("PORT-AU-PRINCE", "Port-au-Prince"),
+        ("new_york", "New_York"),
+        ("dar_es_salaam", "Dar_es_Salaam"),
+        ("mcmurdo", "McMurdo"),
+        ("st_johns", "St_Johns"),
+        ("est5edt", "EST5EDT"),
+        ("gmt+5", "GMT+5"),
+        ("berlin", "Berlin"),
+        ("atlantis", "Atlantis"),
+    ] {
+        assert_eq!(to_camel_case(input), canonical, "{input}");
+    }
+
+    // no two components differ only by case
+    let components: std::collections::HashSet<_> = TZ_VARIANTS
+        .iter()
+        .flat_map(|tz| tz.name().split('/'))
+        .collect();
+    let lowercase: std::collections::HashSet<_> = components
+        .iter()
+        .map(|component| component.to_ascii_lowercase())
+        .collect();
+    assert_eq!(lowercase.len(), components.len());
+}
@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+use actix_web::web;
+use log::{error, info, trace};
+use sd_notify::NotifyState;
+
+use crate::app_context::AppContext;
+
+/// Retry period for the first sync when systemd didn't configure a watchdog
+const SYNC_RETRY: Duration = Duration::from_secs(5);
+
+fn notify(state: NotifyState) {
+    if let Err(err) = sd_notify::notify(&[state]) {
+        error!("Failed to notify systemd : {err}");
+    }
+}
+
+/// Tell systemd we are ready once the first NTP sync succeeded, then keep the
+/// watchdog happy. Both calls are no-ops when not started by systemd.
+pub async fn run(app: web::Data<AppContext>) {
+    // systemd recommends pinging at half the configured WatchdogSec
+    let watchdog = sd_notify::watchdog_enabled().map(|timeout| timeout / 2);
+    let period = watchdog.unwrap_or(SYNC_RETRY);
+    let mut ready = false;
+
+    loop {
+        if !ready {
+            app.get_time().await;
+            if app.is_synced().await {
+                info!("First NTP sync done, notify systemd");
+                notify(NotifyState::Ready);
+                ready = true;
+            }
+        }
+
+        match watchdog {
+            Some(_) => {
+                trace!("Ping systemd watchdog");
+                notify(NotifyState::Watchdog);
+            }
+            None if ready => return,
+            None => {}
+        }
+
+        tokio::time::sleep(period).await;
+    }
+}
@@ -0,0 +1,105 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::{models::Time, tz_database::TzDatabase};
+
+pub const DEFAULT_CAPACITY: usize = 1024;
+/// Entries this many seconds away from the requested second are evicted first
+const MAX_AGE: i64 = 2;
+
+/// Short lived memoization of zone conversions keyed by (zone, unix second),
+/// popular zones are converted once per second instead of once per request
+pub struct ZoneCache {
+    capacity: usize,
+    entries: Mutex<HashMap<(Tz, i64), Time>>,
+}
+
+/// Uncached conversion of the instant (truncated to the second) to the zone
+pub fn convert(db: &TzDatabase, tz: Tz, time: DateTime<Utc>) -> Time {
+    let time = Utc
+        .timestamp_opt(time.timestamp(), 0)
+        .single()
+        .unwrap_or(time);
+    Time::from(db.localize(tz, time)).with_tz(tz.name())
+}
+
+impl ZoneCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(HashMap::with_capacity(capacity)),
+        }
+    }
+
+    pub fn get(&self, db: &TzDatabase, tz: Tz, time: DateTime<Utc>) -> Time {
+        let second = time.timestamp();
+
+        if let Some(ret) = self.entries.lock().unwrap().get(&(tz, second)) {
+            return ret.clone();
+        }
+
+        let ret = convert(db, tz, time);
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.capacity {
+            entries.retain(|(_, sec), _| (second - sec).abs() < MAX_AGE);
+        }
+        if entries.len() >= self.capacity {
+            // everything is fresh, make room by dropping the oldest second
+            if let Some(oldest) = entries.keys().min_by_key(|(_, sec)| *sec).copied() {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert((tz, second), ret.clone());
+
+        ret
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for ZoneCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+#[test]
+fn test_zone_cache_matches_conversion() {
+    let cache = ZoneCache::default();
+    let db = TzDatabase::default();
+    let time = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+
+    let cached = cache.get(&db, chrono_tz::Europe::Berlin, time);
+    assert_eq!(cached, convert(&db, chrono_tz::Europe::Berlin, time));
+    assert_eq!(cache.get(&db, chrono_tz::Europe::Berlin, time), cached);
+    assert_eq!(cache.len(), 1);
+}
+
+#[test]
+fn test_zone_cache_bounded() {
+    let cache = ZoneCache::new(2);
+    let db = TzDatabase::default();
+    let time = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+
+    cache.get(&db, chrono_tz::Europe::Berlin, time);
+    cache.get(&db, chrono_tz::Europe::London, time);
+    cache.get(&db, chrono_tz::Asia::Tokyo, time);
+    assert_eq!(cache.len(), 2);
+
+    // a later second evicts the stale entries
+    cache.get(
+        &db,
+        chrono_tz::Asia::Tokyo,
+        time + chrono::TimeDelta::seconds(10),
+    );
+    assert_eq!(cache.len(), 1);
+}